@@ -0,0 +1,55 @@
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use fastrand::f64;
+
+#[path = "../src/baseline.rs"]
+mod baseline;
+#[path = "../src/framework.rs"]
+mod framework;
+#[path = "../src/simd.rs"]
+mod simd;
+
+use framework::{Executor, all_executors};
+
+fn criterion_config() -> Criterion {
+    Criterion::default().sample_size(10)
+}
+
+/// Every sum of squares kernel on the same input per size, so the comparison plots line them up.
+fn executors_bench(c: &mut Criterion) {
+    let job = framework::sum_of_squares();
+    let (simd_name, simd_kernel) = simd::detect();
+    let mut group = c.benchmark_group("sum_of_squares_executors");
+
+    for &size in &[10000, 100000, 1000000, 10000000] {
+        let inp: Vec<f64> = (0..size).map(|_| f64()).collect();
+        group.throughput(Throughput::Elements(size as u64));
+
+        group.bench_with_input(BenchmarkId::new("sequential", size), &inp, |b, vec| {
+            b.iter(|| baseline::sequential_map_reduce(vec))
+        });
+        group.bench_with_input(BenchmarkId::new("rayon", size), &inp, |b, vec| {
+            b.iter(|| baseline::rayon_map_reduce(vec))
+        });
+        for executor in all_executors() {
+            group.bench_with_input(
+                BenchmarkId::new(format!("framework {}", executor.name()), size),
+                &inp,
+                |b, vec| b.iter(|| executor.execute(&job, vec)),
+            );
+        }
+        group.bench_with_input(
+            BenchmarkId::new(format!("simd {simd_name}"), size),
+            &inp,
+            |b, vec| b.iter(|| simd_kernel(vec)),
+        );
+        group.bench_with_input(
+            BenchmarkId::new(format!("simd_optim_sum {simd_name}"), size),
+            &inp,
+            |b, vec| b.iter(|| simd::simd_optim_sum(vec)),
+        );
+    }
+    group.finish();
+}
+
+criterion_group! {name = benches; config = criterion_config(); targets=executors_bench}
+criterion_main!(benches);
@@ -0,0 +1,49 @@
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use fastrand::f64;
+
+#[path = "../src/framework.rs"]
+mod framework;
+
+use framework::{Executor, MapReduce, all_executors};
+
+fn criterion_config() -> Criterion {
+    Criterion::default().sample_size(10)
+}
+
+/// Runs `job` on every executor for every input size, one criterion group per job. Plugging a
+/// new workload in is one more call to this.
+fn bench_job<I, V, M, R, C>(
+    c: &mut Criterion,
+    name: &str,
+    job: &MapReduce<M, R, V, C>,
+    make_input: impl Fn(usize) -> Vec<I>,
+) where
+    I: Sync,
+    V: Clone + Send + Sync,
+    M: Fn(&I) -> V + Sync,
+    R: Fn(V, V) -> V + Sync,
+    C: Fn(V, V) -> V + Sync,
+{
+    let mut group = c.benchmark_group(name);
+    for &size in &[10000, 100000, 1000000, 10000000] {
+        let inp = make_input(size);
+        for executor in all_executors() {
+            group.bench_with_input(BenchmarkId::new(executor.name(), size), &inp, |b, vec| {
+                b.iter(|| executor.execute(job, vec))
+            });
+        }
+    }
+    group.finish();
+}
+
+fn framework_bench(c: &mut Criterion) {
+    bench_job(
+        c,
+        "framework_sum_of_squares",
+        &framework::sum_of_squares(),
+        |size| (0..size).map(|_| f64()).collect(),
+    );
+}
+
+criterion_group! {name = benches; config = criterion_config(); targets=framework_bench}
+criterion_main!(benches);
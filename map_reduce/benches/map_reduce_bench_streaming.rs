@@ -0,0 +1,61 @@
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+
+#[path = "../src/framework.rs"]
+mod framework;
+#[path = "../src/streaming.rs"]
+mod streaming;
+#[path = "../src/timing.rs"]
+mod timing;
+
+use framework::{Executor, all_executors};
+
+fn criterion_config() -> Criterion {
+    Criterion::default().sample_size(10)
+}
+
+/// Same files the Python side maps, written by `streaming::output_stream_for_python`.
+fn streaming_bench(c: &mut Criterion) {
+    let job = framework::sum_of_squares();
+    let mut group = c.benchmark_group("sum_squares_streaming");
+
+    for &size in &[10000000, 100000000, 1000000000] {
+        let path = streaming::test_stream_path(size);
+        group.throughput(Throughput::Elements(size as u64));
+
+        for executor in all_executors() {
+            group.bench_with_input(
+                BenchmarkId::new(format!("mmap {}", executor.name()), size),
+                &path,
+                |b, path| b.iter(|| streaming::stream_mmap(path, &executor, &job).unwrap()),
+            );
+        }
+        group.bench_with_input(
+            BenchmarkId::new("buffered sequential", size),
+            &path,
+            |b, path| {
+                b.iter(|| {
+                    streaming::stream_buffered(
+                        path,
+                        streaming::DEFAULT_CHUNK,
+                        &framework::Sequential,
+                        &job,
+                    )
+                    .unwrap()
+                })
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("buffered rayon", size),
+            &path,
+            |b, path| {
+                b.iter(|| {
+                    streaming::stream_buffered_rayon(path, streaming::DEFAULT_CHUNK, &job).unwrap()
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group! {name = benches; config = criterion_config(); targets=streaming_bench}
+criterion_main!(benches);
@@ -0,0 +1,51 @@
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+
+#[path = "../src/keyed.rs"]
+mod keyed;
+
+fn criterion_config() -> Criterion {
+    Criterion::default().sample_size(10)
+}
+
+fn bench_corpus(c: &mut Criterion, name: &str, corpora: Vec<(usize, Vec<String>)>) {
+    let job = keyed::word_count_job();
+    let mut group = c.benchmark_group(name);
+
+    for (size, corpus) in corpora {
+        group.throughput(Throughput::Elements(size as u64));
+        group.bench_with_input(BenchmarkId::new("sequential", size), &corpus, |b, lines| {
+            b.iter(|| job.execute_sequential(lines))
+        });
+        for &partitions in &[8, 64] {
+            group.bench_with_input(
+                BenchmarkId::new(format!("rayon-{partitions}"), size),
+                &corpus,
+                |b, lines| b.iter(|| job.execute_rayon(lines, partitions)),
+            );
+        }
+    }
+    group.finish();
+}
+
+fn generated_bench(c: &mut Criterion) {
+    let corpora = [10000, 100000, 1000000, 10000000]
+        .iter()
+        .map(|&size| (size, keyed::generate_corpus(size, 50000)))
+        .collect();
+    bench_corpus(c, "wordcount_generated", corpora);
+}
+
+/// Same corpora the Python side reads, written by `keyed::output_corpus_for_python`.
+fn on_disk_bench(c: &mut Criterion) {
+    let corpora = [10000, 100000, 1000000, 10000000]
+        .iter()
+        .map(|&size| {
+            let corpus = keyed::read_test_corpus(size).expect("corpus read failed");
+            (size, corpus)
+        })
+        .collect();
+    bench_corpus(c, "wordcount_on_disk", corpora);
+}
+
+criterion_group! {name = benches; config = criterion_config(); targets=generated_bench, on_disk_bench}
+criterion_main!(benches);
@@ -0,0 +1,34 @@
+import timeit
+
+import numpy as np
+
+
+def describe(arr: np.ndarray) -> dict:
+    counts, _ = np.histogram(arr, bins=10, range=(0.0, 1.0))
+    return {
+        "count": arr.size,
+        "sum": arr.sum(),
+        "mean": arr.mean(),
+        "var": arr.var(),
+        "min": arr.min(),
+        "max": arr.max(),
+        "histogram": counts,
+    }
+
+
+if __name__ == "__main__":
+    num_runs = 10
+    sizes = [10000, 100000, 1000000, 10000000]
+    res = []
+
+    for size in sizes:
+        arr = np.random.random(size)
+        temp_ = []
+        for _ in range(num_runs):
+            t = timeit.timeit(lambda: describe(arr), number=1)
+            temp_.append(t)
+        res.append(sum(temp_) / len(temp_))
+
+    print("STATS")
+    print("Tested sizes: 1e4, 1e5, 1e6, 1e7")
+    print(f"Averages (timeit), {num_runs} runs: {res}")
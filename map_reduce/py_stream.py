@@ -0,0 +1,36 @@
+import timeit
+
+import numpy as np
+
+CHUNK = 1 << 20
+
+
+def open_stream(size):
+    return np.memmap(f"/home/aperiax/School/SVK/stream_{size}", dtype="<f8", mode="r")
+
+
+def sum_squares_memmap(arr) -> float:
+    # chunked so the squares never need more than one chunk of RAM
+    total = 0.0
+    for start in range(0, arr.shape[0], CHUNK):
+        chunk = arr[start : start + CHUNK]
+        total += float(np.dot(chunk, chunk))
+    return total
+
+
+if __name__ == "__main__":
+    num_runs = 10
+    sizes = [10000000, 100000000, 1000000000]
+    res = []
+
+    for size in sizes:
+        arr = open_stream(size)
+        temp_ = []
+        for _ in range(num_runs):
+            t = timeit.timeit(lambda: sum_squares_memmap(arr), number=1)
+            temp_.append(t)
+        res.append(sum(temp_) / len(temp_))
+
+    print("MPRDC STREAMING")
+    print("Tested sizes: 1e7, 1e8, 1e9 elements")
+    print(f"Averages (timeit), {num_runs} runs: {res}")
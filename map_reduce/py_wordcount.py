@@ -0,0 +1,35 @@
+import re
+import timeit
+from collections import Counter
+
+WORD = re.compile(r"[^\W_]+")
+
+
+def read_corpus(size):
+    with open(f"/home/aperiax/School/SVK/corpus_{size}", "r") as f:
+        return f.read().splitlines()
+
+
+def word_count(lines: list[str]) -> Counter:
+    counts = Counter()
+    for line in lines:
+        counts.update(w.lower() for w in WORD.findall(line))
+    return counts
+
+
+if __name__ == "__main__":
+    num_runs = 10
+    sizes = [10000, 100000, 1000000, 10000000]
+    res = []
+
+    for size in sizes:
+        lines = read_corpus(size)
+        temp_ = []
+        for _ in range(num_runs):
+            t = timeit.timeit(lambda: word_count(lines), number=1)
+            temp_.append(t)
+        res.append(sum(temp_) / len(temp_))
+
+    print("WORDCOUNT")
+    print("Tested sizes: 1e4, 1e5, 1e6, 1e7 words")
+    print(f"Averages (timeit), {num_runs} runs: {res}")
@@ -0,0 +1,29 @@
+//! The two hand-written kernels everything else is measured against, shared with the benches.
+#![allow(dead_code)]
+
+use rayon::prelude::*;
+
+// lifetime specifier and generic type allows
+// us to use this for any collection allowing
+// iteration by reference (vecdeques, hahsmaps, vecs, arrs)
+pub fn sequential_map_reduce<'a, T>(col: T) -> f64
+where
+    T: IntoIterator<Item = &'a f64>,
+{
+    col.into_iter()
+        .map(|x| x * x)
+        // .inspect(|x| println!("{x}"))
+        .sum()
+}
+
+//also like 2 s,
+pub fn rayon_map_reduce<'a, T>(arr: T) -> f64
+where
+    T: IntoParallelIterator<Item = &'a f64>,
+{
+    // chunked version and the chunk size/thread count sweep live in tuning.rs
+    arr.into_par_iter()
+        .map(|x| x * x)
+        // .inspect(|x| println!("{x}"))
+        .sum()
+}
@@ -0,0 +1,191 @@
+//! A small, generic map-reduce: a `MapReduce` job describes what to compute, an `Executor`
+//! decides how. Any job runs on any executor, so a new workload only needs its three closures.
+#![allow(dead_code)]
+
+use rayon::prelude::*;
+
+/// `map` turns every item into a value, `combine` folds values locally (inside one chunk or one
+/// rayon split), `reduce` merges the partial results. Without an explicit combiner `reduce` does
+/// both. `identity` has to be neutral for `combine` and `reduce`.
+pub struct MapReduce<M, R, V, C = fn(V, V) -> V> {
+    pub identity: V,
+    pub map: M,
+    pub reduce: R,
+    pub combine: Option<C>,
+}
+
+impl<M, R, V> MapReduce<M, R, V> {
+    pub fn new(identity: V, map: M, reduce: R) -> Self {
+        MapReduce {
+            identity,
+            map,
+            reduce,
+            combine: None,
+        }
+    }
+}
+
+impl<M, R, V, C> MapReduce<M, R, V, C> {
+    pub fn with_combiner<C2>(self, combine: C2) -> MapReduce<M, R, V, C2> {
+        MapReduce {
+            identity: self.identity,
+            map: self.map,
+            reduce: self.reduce,
+            combine: Some(combine),
+        }
+    }
+}
+
+impl<M, R, V, C> MapReduce<M, R, V, C>
+where
+    V: Clone,
+    R: Fn(V, V) -> V,
+    C: Fn(V, V) -> V,
+{
+    #[inline(always)]
+    fn combine(&self, a: V, b: V) -> V {
+        match &self.combine {
+            Some(c) => c(a, b),
+            None => (self.reduce)(a, b),
+        }
+    }
+
+    /// map + combine over one contiguous run of items, shared by every executor.
+    #[inline(always)]
+    fn fold_local<'a, I: 'a>(&self, items: impl Iterator<Item = &'a I>) -> V
+    where
+        M: Fn(&I) -> V,
+    {
+        items.fold(self.identity.clone(), |acc, x| {
+            self.combine(acc, (self.map)(x))
+        })
+    }
+}
+
+/// The sum of squares every executor started out with.
+pub fn sum_of_squares()
+-> MapReduce<impl Fn(&f64) -> f64 + Sync, impl Fn(f64, f64) -> f64 + Sync, f64> {
+    MapReduce::new(0_f64, |x: &f64| x * x, |a: f64, b: f64| a + b)
+}
+
+pub trait Executor {
+    fn name(&self) -> String;
+
+    fn execute<I, V, M, R, C>(&self, job: &MapReduce<M, R, V, C>, data: &[I]) -> V
+    where
+        I: Sync,
+        V: Clone + Send + Sync,
+        M: Fn(&I) -> V + Sync,
+        R: Fn(V, V) -> V + Sync,
+        C: Fn(V, V) -> V + Sync;
+}
+
+/// Single thread, the whole input is one chunk.
+pub struct Sequential;
+
+/// rayon's adaptive splitting over individual items.
+pub struct Rayon;
+
+/// Fixed size chunks, each folded sequentially and the chunk results reduced in parallel.
+pub struct ChunkedRayon {
+    pub chunk_size: usize,
+}
+
+impl Executor for Sequential {
+    fn name(&self) -> String {
+        "sequential".to_string()
+    }
+
+    fn execute<I, V, M, R, C>(&self, job: &MapReduce<M, R, V, C>, data: &[I]) -> V
+    where
+        I: Sync,
+        V: Clone + Send + Sync,
+        M: Fn(&I) -> V + Sync,
+        R: Fn(V, V) -> V + Sync,
+        C: Fn(V, V) -> V + Sync,
+    {
+        (job.reduce)(job.identity.clone(), job.fold_local(data.iter()))
+    }
+}
+
+impl Executor for Rayon {
+    fn name(&self) -> String {
+        "rayon".to_string()
+    }
+
+    fn execute<I, V, M, R, C>(&self, job: &MapReduce<M, R, V, C>, data: &[I]) -> V
+    where
+        I: Sync,
+        V: Clone + Send + Sync,
+        M: Fn(&I) -> V + Sync,
+        R: Fn(V, V) -> V + Sync,
+        C: Fn(V, V) -> V + Sync,
+    {
+        data.par_iter()
+            .fold(
+                || job.identity.clone(),
+                |acc, x| job.combine(acc, (job.map)(x)),
+            )
+            .reduce(|| job.identity.clone(), |a, b| (job.reduce)(a, b))
+    }
+}
+
+impl Executor for ChunkedRayon {
+    fn name(&self) -> String {
+        format!("chunked_rayon({})", self.chunk_size)
+    }
+
+    fn execute<I, V, M, R, C>(&self, job: &MapReduce<M, R, V, C>, data: &[I]) -> V
+    where
+        I: Sync,
+        V: Clone + Send + Sync,
+        M: Fn(&I) -> V + Sync,
+        R: Fn(V, V) -> V + Sync,
+        C: Fn(V, V) -> V + Sync,
+    {
+        data.par_chunks(self.chunk_size.max(1))
+            .map(|chunk| job.fold_local(chunk.iter()))
+            .reduce(|| job.identity.clone(), |a, b| (job.reduce)(a, b))
+    }
+}
+
+/// Any of the executors above, so they can sit in one list and be looped over.
+pub enum AnyExecutor {
+    Sequential(Sequential),
+    Rayon(Rayon),
+    ChunkedRayon(ChunkedRayon),
+}
+
+impl Executor for AnyExecutor {
+    fn name(&self) -> String {
+        match self {
+            AnyExecutor::Sequential(e) => e.name(),
+            AnyExecutor::Rayon(e) => e.name(),
+            AnyExecutor::ChunkedRayon(e) => e.name(),
+        }
+    }
+
+    fn execute<I, V, M, R, C>(&self, job: &MapReduce<M, R, V, C>, data: &[I]) -> V
+    where
+        I: Sync,
+        V: Clone + Send + Sync,
+        M: Fn(&I) -> V + Sync,
+        R: Fn(V, V) -> V + Sync,
+        C: Fn(V, V) -> V + Sync,
+    {
+        match self {
+            AnyExecutor::Sequential(e) => e.execute(job, data),
+            AnyExecutor::Rayon(e) => e.execute(job, data),
+            AnyExecutor::ChunkedRayon(e) => e.execute(job, data),
+        }
+    }
+}
+
+/// The executors the harness and the benches run every job on.
+pub fn all_executors() -> Vec<AnyExecutor> {
+    vec![
+        AnyExecutor::Sequential(Sequential),
+        AnyExecutor::Rayon(Rayon),
+        AnyExecutor::ChunkedRayon(ChunkedRayon { chunk_size: 4096 }),
+    ]
+}
@@ -0,0 +1,186 @@
+//! Keyed map-reduce: map emits (key, value) pairs, a hash-partitioned shuffle groups them by key
+//! and reduce runs once per key. Word count is the workload that comes with it.
+#![allow(dead_code)]
+
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hash};
+use std::io::BufRead;
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Write},
+};
+
+/// `map` emits any number of pairs per item, `reduce` sees every value emitted for one key.
+pub struct KeyedMapReduce<M, R> {
+    pub map: M,
+    pub reduce: R,
+}
+
+/// Items handed to one map task before its output is partitioned.
+const MAP_CHUNK: usize = 1024;
+
+impl<M, R> KeyedMapReduce<M, R> {
+    pub fn new(map: M, reduce: R) -> Self {
+        KeyedMapReduce { map, reduce }
+    }
+
+    pub fn execute_sequential<I, K, V, O, E>(&self, data: &[I]) -> HashMap<K, O>
+    where
+        K: Hash + Eq,
+        M: Fn(&I) -> E,
+        E: IntoIterator<Item = (K, V)>,
+        R: Fn(&K, Vec<V>) -> O,
+    {
+        let mut groups: HashMap<K, Vec<V>> = HashMap::new();
+        for item in data {
+            for (k, v) in (self.map)(item) {
+                groups.entry(k).or_default().push(v);
+            }
+        }
+
+        groups
+            .into_iter()
+            .map(|(k, vs)| {
+                let out = (self.reduce)(&k, vs);
+                (k, out)
+            })
+            .collect()
+    }
+
+    /// Map tasks write into `partitions` buckets by key hash, then every partition is grouped and
+    /// reduced on its own. A key only ever lands in one partition, so nothing has to be merged
+    /// after the reduce.
+    pub fn execute_rayon<I, K, V, O, E>(&self, data: &[I], partitions: usize) -> HashMap<K, O>
+    where
+        I: Sync,
+        K: Hash + Eq + Send,
+        V: Send,
+        O: Send,
+        M: Fn(&I) -> E + Sync,
+        E: IntoIterator<Item = (K, V)>,
+        R: Fn(&K, Vec<V>) -> O + Sync,
+    {
+        let partitions = partitions.max(1);
+        // one hasher for everybody, the partition of a key has to be the same in every task
+        let hasher = RandomState::new();
+
+        // map: every task buckets its own output
+        let mapped: Vec<Vec<Vec<(K, V)>>> = data
+            .par_chunks(MAP_CHUNK)
+            .map(|chunk| {
+                let mut buckets: Vec<Vec<(K, V)>> = (0..partitions).map(|_| Vec::new()).collect();
+                for item in chunk {
+                    for (k, v) in (self.map)(item) {
+                        let p = (hasher.hash_one(&k) % partitions as u64) as usize;
+                        buckets[p].push((k, v));
+                    }
+                }
+                buckets
+            })
+            .collect();
+
+        // shuffle: transpose task x partition into partition x task
+        let mut by_partition: Vec<Vec<Vec<(K, V)>>> = (0..partitions)
+            .map(|_| Vec::with_capacity(mapped.len()))
+            .collect();
+        for buckets in mapped {
+            for (p, bucket) in buckets.into_iter().enumerate() {
+                by_partition[p].push(bucket);
+            }
+        }
+
+        // group + reduce, one partition per rayon job
+        by_partition
+            .into_par_iter()
+            .flat_map_iter(|buckets| {
+                let mut groups: HashMap<K, Vec<V>> = HashMap::new();
+                for (k, v) in buckets.into_iter().flatten() {
+                    groups.entry(k).or_default().push(v);
+                }
+                groups.into_iter().map(|(k, vs)| {
+                    let out = (self.reduce)(&k, vs);
+                    (k, out)
+                })
+            })
+            .collect()
+    }
+}
+
+/// Lower-cased alphanumeric runs of a line.
+fn words(line: &str) -> impl Iterator<Item = String> + '_ {
+    line.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase())
+}
+
+type WordCountMap = fn(&String) -> Vec<(String, usize)>;
+type WordCountReduce = fn(&String, Vec<usize>) -> usize;
+
+/// One (word, 1) per word, summed per word.
+pub fn word_count_job() -> KeyedMapReduce<WordCountMap, WordCountReduce> {
+    KeyedMapReduce::new(
+        |line: &String| words(line).map(|w| (w, 1)).collect::<Vec<_>>(),
+        |_: &String, counts: Vec<usize>| counts.iter().sum(),
+    )
+}
+
+/// Synthetic word for a vocabulary rank, base-26 so every rank gets a distinct word.
+fn word_for_rank(mut rank: usize) -> String {
+    let mut w = String::new();
+    loop {
+        w.push((b'a' + (rank % 26) as u8) as char);
+        rank /= 26;
+        if rank == 0 {
+            break w;
+        }
+    }
+}
+
+/// `n_words` words in lines of 12, roughly Zipf distributed over `vocab` words (rank r shows up
+/// about 1/r as often as rank 1), which is what natural text looks like to a word count.
+pub fn generate_corpus(n_words: usize, vocab: usize) -> Vec<String> {
+    let vocab = vocab.max(1) as f64;
+    let mut lines = Vec::with_capacity(n_words / 12 + 1);
+    let mut line = String::new();
+
+    for i in 0..n_words {
+        // log-uniform rank is the continuous version of Zipf with s = 1
+        let rank = vocab.powf(fastrand::f64()) as usize - 1;
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(&word_for_rank(rank));
+
+        if (i + 1) % 12 == 0 {
+            lines.push(std::mem::take(&mut line));
+        }
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+pub fn read_corpus(path: &str) -> io::Result<Vec<String>> {
+    let file = File::open(path)?;
+    BufReader::new(file).lines().collect()
+}
+
+pub fn output_corpus_for_python() -> std::io::Result<()> {
+    for &size in &[10000, 100000, 1000000, 10000000] {
+        let file = File::create(format!("/home/aperiax/School/SVK/corpus_{}", size))?;
+        let mut writer = BufWriter::new(file);
+
+        for line in generate_corpus(size, 50000) {
+            writeln!(writer, "{}", line)?;
+        }
+        writer.flush()?;
+    }
+    Ok(())
+}
+
+pub fn read_test_corpus(size: usize) -> io::Result<Vec<String>> {
+    read_corpus(&format!("/home/aperiax/School/SVK/corpus_{}", size))
+}
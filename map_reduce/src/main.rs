@@ -1,98 +1,341 @@
-use rayon::prelude::*;
-use std::arch::x86_64::*;
-use std::time::{Duration, Instant};
-
-// lifetime specifier and generic type allows
-// us to use this for any collection allowing
-// iteration by reference (vecdeques, hahsmaps, vecs, arrs)
-fn sequential_map_reduce<'a, T>(col: T) -> f64
-where
-    T: IntoIterator<Item = &'a f64>,
-{
-    col.into_iter()
-        .map(|x| x * x)
-        // .inspect(|x| println!("{x}"))
-        .sum()
-}
+use crate::baseline::{rayon_map_reduce, sequential_map_reduce};
+use crate::framework::{Executor, MapReduce, all_executors};
+use crate::timing::average_secs;
+
+mod baseline;
+mod framework;
+mod keyed;
+mod simd;
+mod stats;
+mod streaming;
+mod summation;
+mod timing;
+mod tuning;
 
-//also like 2 s,
-fn rayon_map_reduce<'a, T>(arr: T) -> f64
+/// Average time of `num_runs` runs of `job` on every executor, in `all_executors` order.
+fn time_on_all_executors<I, V, M, R, C>(
+    job: &MapReduce<M, R, V, C>,
+    data: &[I],
+    num_runs: usize,
+) -> Vec<(String, f64)>
 where
-    T: IntoParallelIterator<Item = &'a f64>,
+    I: Sync,
+    V: Clone + Send + Sync,
+    M: Fn(&I) -> V + Sync,
+    R: Fn(V, V) -> V + Sync,
+    C: Fn(V, V) -> V + Sync,
 {
-    //TODO: try this with chunking! to see if I can crank the num_threads up
-    arr.into_par_iter()
-        .map(|x| x * x)
-        // .inspect(|x| println!("{x}"))
-        .sum()
+    all_executors()
+        .iter()
+        .map(|executor| {
+            let avg = average_secs(|| executor.execute(job, data), num_runs);
+            (executor.name(), avg)
+        })
+        .collect()
 }
 
-#[inline(always)]
-//NOTE: doesn't really do that well
-fn simd_optim_sum(data: &[f64]) -> f64 {
-    let total: f64 = unsafe {
-        data.par_chunks(1024)
-            .map(|thread_chunk| {
-                let mut sum = _mm256_setzero_pd();
-
-                for subchunk in thread_chunk.chunks_exact(4) {
-                    let x = _mm256_loadu_pd(subchunk.as_ptr());
-                    let prod = _mm256_mul_pd(x, x);
-                    sum = _mm256_add_pd(sum, prod);
-                }
+fn main() {
+    // keyed::output_corpus_for_python().expect("Failed to write corpora");
+    // streaming::output_stream_for_python().expect("Failed to write streams");
+    let job = framework::sum_of_squares();
+    let mut results: Vec<(String, Vec<f64>)> = Vec::new();
+
+    for &size in &[10000, 100000, 1000000, 10000000] {
+        let data: Vec<f64> = (0..size).map(|_| fastrand::f64()).collect();
 
-                let mut tmp = [0_f64; 4];
+        // the hand-written kernels stay in as the baseline the framework has to match
+        let mut timings = vec![
+            (
+                "sequential (hand-written)".to_string(),
+                average_secs(|| sequential_map_reduce(&data), 10),
+            ),
+            (
+                "rayon (hand-written)".to_string(),
+                average_secs(|| rayon_map_reduce(&data), 10),
+            ),
+        ];
+        timings.extend(time_on_all_executors(&job, &data, 10));
 
-                _mm256_store_pd(tmp.as_mut_ptr(), sum);
+        for (i, (name, avg)) in timings.into_iter().enumerate() {
+            if results.len() <= i {
+                results.push((name, Vec::with_capacity(4)));
+            }
+            results[i].1.push(avg);
+        }
+    }
 
-                let scalar_sum = tmp.iter().sum::<f64>()
-                    + thread_chunk
-                        .chunks_exact(4)
-                        .remainder()
-                        .iter()
-                        .map(|f| f * f)
-                        .sum::<f64>();
+    println!("MPRDC");
+    println!("Measuring for 1e4, 1e5, 1e6 and 1e7 elements");
+    for (name, res) in &results {
+        println!("Averages {}: {:?}", name, res);
+    }
 
-                scalar_sum
-            })
-            .sum()
-    };
-    return total;
-}
+    let word_count = keyed::word_count_job();
+    let mut res_wc_seq: Vec<f64> = Vec::with_capacity(4);
+    let mut res_wc_rayon: Vec<f64> = Vec::with_capacity(4);
+    for &size in &[10000, 100000, 1000000, 10000000] {
+        let corpus = keyed::generate_corpus(size, 50000);
+        res_wc_seq.push(average_secs(|| word_count.execute_sequential(&corpus), 10));
+        res_wc_rayon.push(average_secs(|| word_count.execute_rayon(&corpus, 64), 10));
+    }
+
+    println!("WORDCOUNT");
+    println!("Measuring for 1e4, 1e5, 1e6 and 1e7 words");
+    println!("Averages sequential: {:?}", res_wc_seq);
+    println!("Averages rayon: {:?}", res_wc_rayon);
 
-fn measure_raw<T>(mut f: impl FnMut() -> T) -> Duration {
-    let start = Instant::now();
-    let a = f();
-    std::hint::black_box(a);
-    Instant::now() - start
+    tuning::print_sweep_report(&[1000, 10000, 100000, 1000000, 10000000], 10);
+    summation::print_accuracy_report(&[10000, 1000000, 10000000], 10);
+    stats::print_stats_report(&[10000, 100000, 1000000, 10000000], 10);
+    streaming::print_streaming_report(10000000, 10).expect("Streaming report failed");
 }
 
-fn main() {
-    let mut res_seq: Vec<f64> = Vec::with_capacity(4);
-    let mut res_rayon: Vec<f64> = Vec::with_capacity(4);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rayon::prelude::*;
 
-    for &size in &[10000, 100000, 1000000, 10000000] {
-        let data: Vec<f64> = (0..size).map(|_| fastrand::f64()).collect();
-        let mut temp_seq: Vec<Duration> = Vec::with_capacity(10);
-        let mut temp_rayon: Vec<Duration> = Vec::with_capacity(10);
-
-        for _ in 0..10 {
-            let time_seq = measure_raw(|| sequential_map_reduce(&data));
-            let time_rayon = measure_raw(|| rayon_map_reduce(&data));
-            temp_seq.push(time_seq);
-            temp_rayon.push(time_rayon);
+    fn assert_close(got: f64, expected: f64, what: &str) {
+        let tol = 1e-12 * expected.abs().max(1.);
+        assert!((got - expected).abs() <= tol, "{what}: {got} vs {expected}");
+    }
+
+    #[test]
+    fn simd_kernels_match_sequential() {
+        let data: Vec<f64> = (0..10_003).map(|_| fastrand::f64() * 2. - 1.).collect();
+
+        for (name, kernel) in simd::available() {
+            for len in [0, 1, 3, 7, 8, 9, 1023, 1025, 10_003] {
+                // starting at 1 shifts the slice off any 16/32/64 byte boundary
+                for start in [0, 1] {
+                    let slice = &data[start.min(len)..len];
+                    assert_close(
+                        kernel(slice),
+                        sequential_map_reduce(slice),
+                        &format!("{name}, len {len}, offset {start}"),
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn parallel_simd_matches_sequential() {
+        let data: Vec<f64> = (0..100_001).map(|_| fastrand::f64()).collect();
+        assert_close(
+            simd::simd_optim_sum(&data[1..]),
+            sequential_map_reduce(&data[1..]),
+            "simd_optim_sum",
+        );
+        assert_close(
+            simd::simd_sum_squares(&data),
+            rayon_map_reduce(&data),
+            "simd_sum_squares",
+        );
+    }
+
+    #[test]
+    fn executors_agree_on_every_job() {
+        let data: Vec<f64> = (0..50_001).map(|_| fastrand::f64()).collect();
+        let expected = sequential_map_reduce(&data);
+
+        let sum_sq = framework::sum_of_squares();
+        // (count, max) with a combiner that only ever sees one chunk's worth of values
+        let count_max = MapReduce::new(
+            (0_usize, f64::MIN),
+            |x: &f64| (1, *x),
+            |a: (usize, f64), b: (usize, f64)| (a.0 + b.0, a.1.max(b.1)),
+        )
+        .with_combiner(|a: (usize, f64), b: (usize, f64)| (a.0 + b.0, a.1.max(b.1)));
+        let max = data.iter().cloned().fold(f64::MIN, f64::max);
+
+        for executor in all_executors() {
+            assert_close(executor.execute(&sum_sq, &data), expected, &executor.name());
+            assert_eq!(executor.execute(&count_max, &data), (data.len(), max));
+            assert_eq!(executor.execute(&count_max, &data[..0]), (0, f64::MIN));
         }
+    }
+
+    #[test]
+    fn word_count_sequential_and_rayon_agree() {
+        let lines: Vec<String> = ["The cat, the hat.", "", "a CAT sat on the mat"]
+            .iter()
+            .map(|l| l.to_string())
+            .collect();
+        let job = keyed::word_count_job();
+
+        let counts = job.execute_sequential(&lines);
+        assert_eq!(counts["the"], 3);
+        assert_eq!(counts["cat"], 2);
+        assert_eq!(counts.len(), 7);
+        assert_eq!(job.execute_rayon(&lines, 4), counts);
+
+        let corpus = keyed::generate_corpus(20_000, 500);
+        let counts = job.execute_sequential(&corpus);
+        assert_eq!(counts.values().sum::<usize>(), 20_000);
+        assert_eq!(job.execute_rayon(&corpus, 7), counts);
+    }
+
+    #[test]
+    fn stats_variants_match_two_pass() {
+        use stats::{Bins, VARIANTS, describe_par_iter};
 
-        res_seq.push(temp_seq.iter().map(|i| i.as_secs_f64()).sum::<f64>() / temp_seq.len() as f64);
-        println!("{}", res_seq.len());
+        // large offset, small spread: sum of squares minus squared sum would lose everything here
+        let data: Vec<f64> = (0..20_011).map(|_| 1e9 + fastrand::f64()).collect();
+        let n = data.len() as f64;
+        let mean = data.iter().sum::<f64>() / n;
+        let var = data.iter().map(|x| (x - mean) * (x - mean)).sum::<f64>() / n;
+        let min = data.iter().cloned().fold(f64::INFINITY, f64::min);
+        let max = data.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let bins = Bins::new(1e9, 1e9 + 0.5, 8);
 
-        res_rayon.push(
-            temp_rayon.iter().map(|i| i.as_secs_f64()).sum::<f64>() / temp_rayon.len() as f64,
+        let reference = stats::describe_sequential(&data, bins);
+        let hist = &reference.histogram;
+        assert_eq!(
+            hist.counts.iter().sum::<u64>() + hist.above,
+            data.len() as u64
         );
+        assert_eq!(hist.below, 0);
+
+        let par_iter = describe_par_iter(data.par_iter().cloned(), bins);
+        let results = VARIANTS.iter().map(|(name, f)| (*name, f(&data, bins)));
+        for (name, s) in results.chain([("par_iter", par_iter)]) {
+            assert_eq!(s.count, data.len() as u64, "{name}");
+            assert_eq!((s.min, s.max), (min, max), "{name}");
+            assert_close(s.mean, mean, name);
+            assert!(
+                ((s.variance() - var) / var).abs() < 1e-6,
+                "{name}: {}",
+                s.variance()
+            );
+            assert_eq!(s.histogram, reference.histogram, "{name}");
+        }
+
+        for (name, f) in VARIANTS {
+            let empty = f(&[], bins);
+            assert_eq!(empty.count, 0, "{name}");
+            assert!(empty.variance().is_nan(), "{name}");
+            assert!(f(&[2.], bins).sample_variance().is_nan(), "{name}");
+        }
     }
 
-    println!("MPRDC");
-    println!("Measuring for 1e4, 1e5, 1e6 and 1e7 elements");
-    println!("Averages sequential: {:?}", res_seq);
-    println!("Averages rayon: {:?}", res_rayon);
+    #[test]
+    fn streaming_readers_match_in_memory() {
+        use streaming::{stream_buffered, stream_buffered_rayon, stream_mmap, write_random_file};
+
+        let path = std::env::temp_dir().join(format!("map_reduce_test_{}", std::process::id()));
+        // not a multiple of any chunk size used below
+        write_random_file(&path, 10_007).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        let data: Vec<f64> = bytes
+            .chunks_exact(8)
+            .map(|b| f64::from_le_bytes(b.try_into().unwrap()))
+            .collect();
+        let expected = sequential_map_reduce(&data);
+        let job = framework::sum_of_squares();
+
+        for executor in all_executors() {
+            assert_close(
+                stream_mmap(&path, &executor, &job).unwrap(),
+                expected,
+                "mmap",
+            );
+            for chunk in [1, 1000, 1 << 20] {
+                let name = format!("buffered {}, chunk {chunk}", executor.name());
+                assert_close(
+                    stream_buffered(&path, chunk, &executor, &job).unwrap(),
+                    expected,
+                    &name,
+                );
+            }
+        }
+        for chunk in [1, 1000, 1 << 20] {
+            let got = stream_buffered_rayon(&path, chunk, &job).unwrap();
+            assert_close(got, expected, &format!("buffered rayon, chunk {chunk}"));
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one chunking")]
+    fn sweep_rejects_empty_chunkings() {
+        tuning::sweep(&[16], &[], &[1], 1);
+    }
+
+    #[test]
+    fn chunked_rayon_and_crossover() {
+        use tuning::{BestConfig, Chunking, SweepResult, crossover, rayon_map_reduce_chunked};
+
+        let data: Vec<f64> = (0..10_007).map(|_| fastrand::f64() * 2. - 1.).collect();
+        let n = data.len();
+        let expected = sequential_map_reduce(&data);
+        for chunk in [1, n, n + 1] {
+            for chunking in [Chunking::MinLen(chunk), Chunking::ParChunks(chunk)] {
+                let got = rayon_map_reduce_chunked(&data, chunking);
+                assert_close(got, expected, &format!("{chunking:?}"));
+            }
+        }
+        assert_eq!(rayon_map_reduce_chunked(&[], Chunking::ParChunks(4)), 0.);
+
+        let result = |size, threads, secs| SweepResult {
+            size,
+            threads,
+            chunking: Chunking::ParChunks(1024),
+            secs,
+        };
+        let swept = |sequential_secs, results: Vec<SweepResult>| {
+            let best = *results
+                .iter()
+                .min_by(|a, b| a.secs.total_cmp(&b.secs))
+                .unwrap();
+            (
+                BestConfig {
+                    best,
+                    sequential_secs,
+                },
+                results,
+            )
+        };
+        // a one-thread pool beating the sequential loop is not a crossover
+        let small = swept(1., vec![result(100, 1, 0.5), result(100, 2, 2.)]);
+        let large = swept(1., vec![result(10_000, 1, 2.), result(10_000, 2, 0.5)]);
+        assert_eq!(crossover(&[small.clone(), large]), Some(10_000));
+        assert_eq!(crossover(&[small]), None);
+        assert_eq!(crossover(&[]), None);
+    }
+
+    #[test]
+    fn summation_modes_agree_with_exact() {
+        use summation::{Backend, Summation, sum_squares};
+
+        // a few huge terms among many tiny ones, where naive summation visibly loses digits
+        let mut rng = fastrand::Rng::with_seed(36);
+        let mut data: Vec<f64> = (0..100_000).map(|_| rng.f64() * 1e-4).collect();
+        data[10] = 1e4;
+        data[50_000] = 3e4;
+        let exact = sum_squares(&data, Summation::Exact, Backend::Sequential);
+        let rel_error = |mode, backend| (sum_squares(&data, mode, backend) - exact).abs() / exact;
+
+        for backend in Backend::ALL {
+            // exact does not depend on the order of additions, so every backend agrees bit for bit
+            assert_eq!(sum_squares(&data, Summation::Exact, backend), exact);
+            for mode in Summation::ALL {
+                let got = sum_squares(&data, mode, backend);
+                assert_close(got, exact, &format!("{mode:?} on {backend:?}"));
+            }
+
+            // the tiny squares are below half an ulp of the running sum, naive drops them
+            let naive = rel_error(Summation::Naive, backend);
+            assert!(naive > 1e-15, "naive on {backend:?} only off by {naive:e}");
+            for mode in [Summation::Kahan, Summation::Neumaier, Summation::Exact] {
+                let err = rel_error(mode, backend);
+                assert!(
+                    err < naive,
+                    "{mode:?} on {backend:?}: {err:e}, naive {naive:e}"
+                );
+            }
+        }
+        assert_eq!(sum_squares(&[], Summation::Exact, Backend::Simd), 0.);
+        assert_eq!(sum_squares(&[3.], Summation::Neumaier, Backend::Rayon), 9.);
+    }
 }
@@ -0,0 +1,135 @@
+//! Sum of squares with explicit SIMD, picked at runtime from what the CPU actually supports.
+//!
+//! The x86_64 kernels only exist on x86_64, everything else (and x86_64 machines without SSE2,
+//! if there are any left) gets the portable scalar version. All loads and stores are the
+//! unaligned variants, `Vec<f64>` is only guaranteed 8-byte aligned.
+#![allow(dead_code)]
+
+use rayon::prelude::*;
+
+/// Signature shared by every kernel, so the dispatch can hand out plain fn pointers.
+pub type SumSquares = fn(&[f64]) -> f64;
+
+/// Four independent accumulators, enough for the compiler to vectorize it on its own.
+pub fn scalar(data: &[f64]) -> f64 {
+    let mut acc = [0_f64; 4];
+    let chunks = data.chunks_exact(4);
+    let rem = chunks.remainder();
+
+    for chunk in chunks {
+        for (a, x) in acc.iter_mut().zip(chunk) {
+            *a += x * x;
+        }
+    }
+    acc.iter().sum::<f64>() + rem.iter().map(|x| x * x).sum::<f64>()
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use std::arch::x86_64::*;
+
+    #[target_feature(enable = "avx512f")]
+    pub fn avx512(data: &[f64]) -> f64 {
+        let chunks = data.chunks_exact(8);
+        let rem = chunks.remainder();
+
+        let mut sum = _mm512_setzero_pd();
+        for chunk in chunks {
+            // SAFETY: chunks_exact guarantees 8 readable f64s
+            let x = unsafe { _mm512_loadu_pd(chunk.as_ptr()) };
+            sum = _mm512_fmadd_pd(x, x, sum);
+        }
+        _mm512_reduce_add_pd(sum) + rem.iter().map(|x| x * x).sum::<f64>()
+    }
+
+    #[target_feature(enable = "avx2,fma")]
+    pub fn avx2_fma(data: &[f64]) -> f64 {
+        let chunks = data.chunks_exact(4);
+        let rem = chunks.remainder();
+
+        let mut sum = _mm256_setzero_pd();
+        for chunk in chunks {
+            // SAFETY: chunks_exact guarantees 4 readable f64s
+            let x = unsafe { _mm256_loadu_pd(chunk.as_ptr()) };
+            sum = _mm256_fmadd_pd(x, x, sum);
+        }
+
+        let mut tmp = [0_f64; 4];
+        // SAFETY: tmp holds 4 f64s, storeu has no alignment requirement
+        unsafe { _mm256_storeu_pd(tmp.as_mut_ptr(), sum) };
+        tmp.iter().sum::<f64>() + rem.iter().map(|x| x * x).sum::<f64>()
+    }
+
+    #[target_feature(enable = "sse2")]
+    pub fn sse2(data: &[f64]) -> f64 {
+        let chunks = data.chunks_exact(2);
+        let rem = chunks.remainder();
+
+        let mut sum = _mm_setzero_pd();
+        for chunk in chunks {
+            // SAFETY: chunks_exact guarantees 2 readable f64s
+            let x = unsafe { _mm_loadu_pd(chunk.as_ptr()) };
+            sum = _mm_add_pd(sum, _mm_mul_pd(x, x));
+        }
+
+        let mut tmp = [0_f64; 2];
+        // SAFETY: tmp holds 2 f64s, storeu has no alignment requirement
+        unsafe { _mm_storeu_pd(tmp.as_mut_ptr(), sum) };
+        tmp.iter().sum::<f64>() + rem.iter().map(|x| x * x).sum::<f64>()
+    }
+
+    // The wrappers below are only ever handed out by `detect`/`available` after the matching
+    // `is_x86_feature_detected!` check, which is what makes calling them sound.
+
+    pub fn avx512_checked(data: &[f64]) -> f64 {
+        // SAFETY: see above
+        unsafe { avx512(data) }
+    }
+
+    pub fn avx2_fma_checked(data: &[f64]) -> f64 {
+        // SAFETY: see above
+        unsafe { avx2_fma(data) }
+    }
+
+    pub fn sse2_checked(data: &[f64]) -> f64 {
+        // SAFETY: see above
+        unsafe { sse2(data) }
+    }
+}
+
+/// Every kernel this CPU can run, best first. The scalar one is always last.
+pub fn available() -> Vec<(&'static str, SumSquares)> {
+    let mut out: Vec<(&'static str, SumSquares)> = Vec::new();
+
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx512f") {
+            out.push(("avx512", x86::avx512_checked));
+        }
+        if is_x86_feature_detected!("avx2") && is_x86_feature_detected!("fma") {
+            out.push(("avx2+fma", x86::avx2_fma_checked));
+        }
+        if is_x86_feature_detected!("sse2") {
+            out.push(("sse2", x86::sse2_checked));
+        }
+    }
+
+    out.push(("scalar", scalar));
+    out
+}
+
+/// The best kernel for this CPU.
+pub fn detect() -> (&'static str, SumSquares) {
+    available()[0]
+}
+
+/// Single-threaded SIMD sum of squares.
+pub fn simd_sum_squares(data: &[f64]) -> f64 {
+    detect().1(data)
+}
+
+/// SIMD within a chunk, rayon across chunks. Dispatch happens once, not per chunk.
+pub fn simd_optim_sum(data: &[f64]) -> f64 {
+    let kernel = detect().1;
+    data.par_chunks(1024).map(kernel).sum()
+}
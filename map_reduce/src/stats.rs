@@ -0,0 +1,356 @@
+//! One pass descriptive statistics: count, sum, mean, variance, min, max and a fixed-bin
+//! histogram, mergeable across threads.
+//!
+//! Mean and variance use Welford's update per item and Chan et al.'s formula to merge two partial
+//! results, so unlike sum / sum of squares they don't fall apart when the mean is large compared
+//! to the spread. The SIMD variant works in cache-sized blocks: one vector pass for sum/min/max,
+//! a second one over the same (still cached) block for the centered squares, then Chan to merge
+//! the block in. Memory is still only read once.
+#![allow(dead_code)]
+
+use rayon::prelude::*;
+
+/// Items per rayon job and per SIMD block.
+const BLOCK: usize = 4096;
+
+/// Equal width bins over [lo, hi), anything outside is only counted.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bins {
+    pub lo: f64,
+    pub hi: f64,
+    pub n: usize,
+}
+
+impl Bins {
+    pub fn new(lo: f64, hi: f64, n: usize) -> Self {
+        assert!(lo < hi, "empty histogram range");
+        Bins {
+            lo,
+            hi,
+            n: n.max(1),
+        }
+    }
+}
+
+/// Approximate in the sense that only the bin of every value is kept, not the value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Histogram {
+    pub bins: Bins,
+    pub counts: Vec<u64>,
+    pub below: u64,
+    pub above: u64,
+}
+
+impl Histogram {
+    pub fn new(bins: Bins) -> Self {
+        Histogram {
+            bins,
+            counts: vec![0; bins.n],
+            below: 0,
+            above: 0,
+        }
+    }
+
+    #[inline(always)]
+    pub fn add(&mut self, x: f64) {
+        let Bins { lo, hi, n } = self.bins;
+        if x < lo {
+            self.below += 1;
+        } else if x >= hi {
+            self.above += 1;
+        } else {
+            // rounding can push a value just under hi into bin n
+            let i = ((x - lo) / (hi - lo) * n as f64) as usize;
+            self.counts[i.min(n - 1)] += 1;
+        }
+    }
+
+    pub fn merge(&mut self, other: &Histogram) {
+        debug_assert_eq!(
+            self.bins, other.bins,
+            "merging histograms with different bins"
+        );
+        for (a, b) in self.counts.iter_mut().zip(&other.counts) {
+            *a += b;
+        }
+        self.below += other.below;
+        self.above += other.above;
+    }
+
+    /// Lower edge of bin i.
+    pub fn edge(&self, i: usize) -> f64 {
+        let Bins { lo, hi, n } = self.bins;
+        lo + (hi - lo) * i as f64 / n as f64
+    }
+}
+
+/// Running statistics. `m2` is the sum of squared deviations from the current mean.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stats {
+    pub count: u64,
+    pub sum: f64,
+    pub mean: f64,
+    pub m2: f64,
+    pub min: f64,
+    pub max: f64,
+    pub histogram: Histogram,
+}
+
+impl Stats {
+    pub fn new(bins: Bins) -> Self {
+        Stats {
+            count: 0,
+            sum: 0.,
+            mean: 0.,
+            m2: 0.,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            histogram: Histogram::new(bins),
+        }
+    }
+
+    /// Welford's update.
+    #[inline(always)]
+    pub fn push(&mut self, x: f64) {
+        self.count += 1;
+        self.sum += x;
+        let delta = x - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (x - self.mean);
+        self.min = self.min.min(x);
+        self.max = self.max.max(x);
+        self.histogram.add(x);
+    }
+
+    /// Chan et al.'s pairwise merge, exact in exact arithmetic for any split of the input.
+    pub fn merge(&mut self, other: &Stats) {
+        if other.count == 0 {
+            return;
+        }
+        if self.count == 0 {
+            self.clone_from(other);
+            return;
+        }
+        let (na, nb) = (self.count as f64, other.count as f64);
+        let n = na + nb;
+        let delta = other.mean - self.mean;
+
+        self.count += other.count;
+        self.sum += other.sum;
+        self.mean += delta * nb / n;
+        self.m2 += other.m2 + delta * delta * na * nb / n;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        self.histogram.merge(&other.histogram);
+    }
+
+    /// Population variance, NaN when empty.
+    pub fn variance(&self) -> f64 {
+        self.m2 / self.count as f64
+    }
+
+    /// Unbiased sample variance, NaN for fewer than two values.
+    pub fn sample_variance(&self) -> f64 {
+        if self.count < 2 {
+            return f64::NAN;
+        }
+        self.m2 / (self.count - 1) as f64
+    }
+
+    pub fn std_dev(&self) -> f64 {
+        self.variance().sqrt()
+    }
+}
+
+fn merged(mut a: Stats, b: Stats) -> Stats {
+    a.merge(&b);
+    a
+}
+
+pub fn describe_sequential(data: &[f64], bins: Bins) -> Stats {
+    let mut stats = Stats::new(bins);
+    for &x in data {
+        stats.push(x);
+    }
+    stats
+}
+
+pub fn describe_rayon(data: &[f64], bins: Bins) -> Stats {
+    data.par_chunks(BLOCK)
+        .map(|chunk| describe_sequential(chunk, bins))
+        .reduce(|| Stats::new(bins), merged)
+}
+
+/// Same as `describe_rayon` for anything rayon can iterate, e.g. a mapped or filtered input.
+pub fn describe_par_iter(iter: impl ParallelIterator<Item = f64>, bins: Bins) -> Stats {
+    iter.fold(
+        || Stats::new(bins),
+        |mut s, x| {
+            s.push(x);
+            s
+        },
+    )
+    .reduce(|| Stats::new(bins), merged)
+}
+
+/// Vector kernels for one block: (sum, min, max), then the centered sum of squares.
+#[derive(Clone, Copy)]
+pub struct BlockKernels {
+    pub sum_min_max: fn(&[f64]) -> (f64, f64, f64),
+    pub centered_squares: fn(&[f64], f64) -> f64,
+}
+
+pub fn scalar_sum_min_max(data: &[f64]) -> (f64, f64, f64) {
+    data.iter()
+        .fold((0., f64::INFINITY, f64::NEG_INFINITY), |(s, lo, hi), &x| {
+            (s + x, lo.min(x), hi.max(x))
+        })
+}
+
+pub fn scalar_centered_squares(data: &[f64], mean: f64) -> f64 {
+    data.iter().map(|x| (x - mean) * (x - mean)).sum()
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use std::arch::x86_64::*;
+
+    #[target_feature(enable = "avx")]
+    fn horizontal(v: __m256d, f: fn(f64, f64) -> f64) -> f64 {
+        let mut tmp = [0_f64; 4];
+        // SAFETY: tmp holds 4 f64s, storeu has no alignment requirement
+        unsafe { _mm256_storeu_pd(tmp.as_mut_ptr(), v) };
+        tmp[1..].iter().fold(tmp[0], |a, &b| f(a, b))
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub fn sum_min_max(data: &[f64]) -> (f64, f64, f64) {
+        let chunks = data.chunks_exact(4);
+        let rem = chunks.remainder();
+
+        let mut sum = _mm256_setzero_pd();
+        let mut lo = _mm256_set1_pd(f64::INFINITY);
+        let mut hi = _mm256_set1_pd(f64::NEG_INFINITY);
+        for chunk in chunks {
+            // SAFETY: chunks_exact guarantees 4 readable f64s
+            let x = unsafe { _mm256_loadu_pd(chunk.as_ptr()) };
+            sum = _mm256_add_pd(sum, x);
+            lo = _mm256_min_pd(lo, x);
+            hi = _mm256_max_pd(hi, x);
+        }
+
+        let (s, l, h) = super::scalar_sum_min_max(rem);
+        (
+            horizontal(sum, |a, b| a + b) + s,
+            horizontal(lo, f64::min).min(l),
+            horizontal(hi, f64::max).max(h),
+        )
+    }
+
+    #[target_feature(enable = "avx2,fma")]
+    pub fn centered_squares(data: &[f64], mean: f64) -> f64 {
+        let chunks = data.chunks_exact(4);
+        let rem = chunks.remainder();
+
+        let m = _mm256_set1_pd(mean);
+        let mut acc = _mm256_setzero_pd();
+        for chunk in chunks {
+            // SAFETY: chunks_exact guarantees 4 readable f64s
+            let d = _mm256_sub_pd(unsafe { _mm256_loadu_pd(chunk.as_ptr()) }, m);
+            acc = _mm256_fmadd_pd(d, d, acc);
+        }
+        horizontal(acc, |a, b| a + b) + super::scalar_centered_squares(rem, mean)
+    }
+
+    // Only handed out by `block_kernels` after checking for avx2 and fma.
+
+    pub fn sum_min_max_checked(data: &[f64]) -> (f64, f64, f64) {
+        // SAFETY: see above
+        unsafe { sum_min_max(data) }
+    }
+
+    pub fn centered_squares_checked(data: &[f64], mean: f64) -> f64 {
+        // SAFETY: see above
+        unsafe { centered_squares(data, mean) }
+    }
+}
+
+/// AVX2 kernels when the CPU has them, the scalar ones otherwise.
+pub fn block_kernels() -> BlockKernels {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx2") && is_x86_feature_detected!("fma") {
+            return BlockKernels {
+                sum_min_max: x86::sum_min_max_checked,
+                centered_squares: x86::centered_squares_checked,
+            };
+        }
+    }
+    BlockKernels {
+        sum_min_max: scalar_sum_min_max,
+        centered_squares: scalar_centered_squares,
+    }
+}
+
+/// Statistics of one block, the mean from the first vector pass centers the second one.
+fn describe_block(block: &[f64], bins: Bins, kernels: BlockKernels) -> Stats {
+    let mut stats = Stats::new(bins);
+    if block.is_empty() {
+        return stats;
+    }
+    let (sum, min, max) = (kernels.sum_min_max)(block);
+    let mean = sum / block.len() as f64;
+
+    stats.count = block.len() as u64;
+    stats.sum = sum;
+    stats.mean = mean;
+    stats.m2 = (kernels.centered_squares)(block, mean);
+    stats.min = min;
+    stats.max = max;
+    for &x in block {
+        stats.histogram.add(x);
+    }
+    stats
+}
+
+/// Single-threaded SIMD variant.
+pub fn describe_simd(data: &[f64], bins: Bins) -> Stats {
+    let kernels = block_kernels();
+    data.chunks(BLOCK)
+        .map(|block| describe_block(block, bins, kernels))
+        .fold(Stats::new(bins), merged)
+}
+
+/// SIMD within a block, rayon across blocks.
+pub fn describe_simd_rayon(data: &[f64], bins: Bins) -> Stats {
+    let kernels = block_kernels();
+    data.par_chunks(BLOCK)
+        .map(|block| describe_block(block, bins, kernels))
+        .reduce(|| Stats::new(bins), merged)
+}
+
+type Describe = fn(&[f64], Bins) -> Stats;
+
+pub const VARIANTS: [(&str, Describe); 4] = [
+    ("sequential", describe_sequential),
+    ("rayon", describe_rayon),
+    ("simd", describe_simd),
+    ("simd_rayon", describe_simd_rayon),
+];
+
+pub fn print_stats_report(sizes: &[usize], num_runs: usize) {
+    let bins = Bins::new(0., 1., 10);
+
+    println!("STATS");
+    println!("Measuring for {:?} elements", sizes);
+    for (name, describe) in VARIANTS {
+        let res: Vec<f64> = sizes
+            .iter()
+            .map(|&size| {
+                let data: Vec<f64> = (0..size).map(|_| fastrand::f64()).collect();
+                crate::timing::average_secs(|| describe(&data, bins), num_runs)
+            })
+            .collect();
+        println!("Averages {}: {:?}", name, res);
+    }
+}
@@ -0,0 +1,208 @@
+//! Out-of-core map-reduce over files of raw little-endian f64s.
+//!
+//! Two ways in: a memory map, where the whole file looks like one `&[f64]` and the OS pages it
+//! in and out behind our back, and buffered chunked reads into a fixed amount of memory. Either
+//! way the input can be much larger than RAM, which the in-memory benchmarks never get near.
+#![allow(dead_code)]
+
+use memmap2::Mmap;
+use rayon::prelude::*;
+use std::{
+    fs::File,
+    io::{self, BufWriter, Read, Write},
+    path::Path,
+    sync::mpsc,
+    thread,
+};
+
+use crate::framework::{Executor, MapReduce};
+
+const F64_BYTES: usize = std::mem::size_of::<f64>();
+
+/// Elements per chunk for the buffered readers, 8 MiB worth of f64s.
+pub const DEFAULT_CHUNK: usize = 1 << 20;
+
+/// Chunks the parallel reader may have in flight before it waits for the workers.
+const IN_FLIGHT: usize = 4;
+
+/// Writes `n` random f64s in [0, 1) without ever holding more than one chunk in memory.
+pub fn write_random_file(path: impl AsRef<Path>, n: usize) -> io::Result<()> {
+    let file = File::create(path)?;
+    let mut writer = BufWriter::new(file);
+    let mut buf: Vec<u8> = Vec::with_capacity(DEFAULT_CHUNK * F64_BYTES);
+
+    let mut written = 0;
+    while written < n {
+        let len = DEFAULT_CHUNK.min(n - written);
+        buf.clear();
+        for _ in 0..len {
+            buf.extend_from_slice(&fastrand::f64().to_le_bytes());
+        }
+        writer.write_all(&buf)?;
+        written += len;
+    }
+    writer.flush()
+}
+
+pub fn output_stream_for_python() -> io::Result<()> {
+    for &size in &[10000000, 100000000, 1000000000] {
+        write_random_file(format!("/home/aperiax/School/SVK/stream_{}", size), size)?;
+    }
+    Ok(())
+}
+
+pub fn test_stream_path(size: usize) -> String {
+    format!("/home/aperiax/School/SVK/stream_{}", size)
+}
+
+/// Fills `buf` with as many values as fit in `bytes`, returns false once the file is exhausted.
+fn read_chunk(reader: &mut impl Read, bytes: &mut [u8], buf: &mut Vec<f64>) -> io::Result<bool> {
+    let mut filled = 0;
+    while filled < bytes.len() {
+        match reader.read(&mut bytes[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+
+    buf.clear();
+    // a trailing partial value is dropped, same as the memory map does
+    buf.extend(
+        bytes[..filled - filled % F64_BYTES]
+            .chunks_exact(F64_BYTES)
+            .map(|b| f64::from_le_bytes(b.try_into().unwrap())),
+    );
+    Ok(!buf.is_empty())
+}
+
+/// Maps the file and runs `job` on it with `executor` as if it were an in-memory slice.
+pub fn stream_mmap<E, V, M, R, C>(
+    path: impl AsRef<Path>,
+    executor: &E,
+    job: &MapReduce<M, R, V, C>,
+) -> io::Result<V>
+where
+    E: Executor,
+    V: Clone + Send + Sync,
+    M: Fn(&f64) -> V + Sync,
+    R: Fn(V, V) -> V + Sync,
+    C: Fn(V, V) -> V + Sync,
+{
+    if cfg!(target_endian = "big") {
+        // the bytes can't be reinterpreted in place, fall back to decoding them
+        return stream_buffered(path, DEFAULT_CHUNK, executor, job);
+    }
+
+    let file = File::open(path)?;
+    // SAFETY: the benchmark files are written once up front and never modified while mapped
+    let mmap = unsafe { Mmap::map(&file)? };
+    let usable = mmap.len() - mmap.len() % F64_BYTES;
+    // page aligned, so the cast can only fail on a broken mapping
+    let data: &[f64] = bytemuck::try_cast_slice(&mmap[..usable])
+        .map_err(|e| io::Error::other(format!("mapping is not f64 aligned: {e}")))?;
+
+    Ok(executor.execute(job, data))
+}
+
+/// Reads `chunk` values at a time into one reused buffer, each chunk runs on `executor` and the
+/// per-chunk results are reduced in file order.
+pub fn stream_buffered<E, V, M, R, C>(
+    path: impl AsRef<Path>,
+    chunk: usize,
+    executor: &E,
+    job: &MapReduce<M, R, V, C>,
+) -> io::Result<V>
+where
+    E: Executor,
+    V: Clone + Send + Sync,
+    M: Fn(&f64) -> V + Sync,
+    R: Fn(V, V) -> V + Sync,
+    C: Fn(V, V) -> V + Sync,
+{
+    let mut file = File::open(path)?;
+    let mut bytes = vec![0_u8; chunk.max(1) * F64_BYTES];
+    let mut buf: Vec<f64> = Vec::with_capacity(chunk.max(1));
+
+    let mut acc = job.identity.clone();
+    while read_chunk(&mut file, &mut bytes, &mut buf)? {
+        acc = (job.reduce)(acc, executor.execute(job, &buf));
+    }
+    Ok(acc)
+}
+
+/// One thread reads chunks, rayon maps them in parallel across chunks (each chunk sequentially).
+/// At most `IN_FLIGHT` chunks wait in the channel, so memory stays bounded no matter the file.
+pub fn stream_buffered_rayon<V, M, R, C>(
+    path: impl AsRef<Path>,
+    chunk: usize,
+    job: &MapReduce<M, R, V, C>,
+) -> io::Result<V>
+where
+    V: Clone + Send + Sync,
+    M: Fn(&f64) -> V + Sync,
+    R: Fn(V, V) -> V + Sync,
+    C: Fn(V, V) -> V + Sync,
+{
+    let mut file = File::open(path)?;
+    let chunk = chunk.max(1);
+    let (tx, rx) = mpsc::sync_channel::<Vec<f64>>(IN_FLIGHT);
+
+    thread::scope(|s| {
+        let reader = s.spawn(move || -> io::Result<()> {
+            let mut bytes = vec![0_u8; chunk * F64_BYTES];
+            loop {
+                let mut buf = Vec::with_capacity(chunk);
+                if !read_chunk(&mut file, &mut bytes, &mut buf)? || tx.send(buf).is_err() {
+                    return Ok(());
+                }
+            }
+        });
+
+        let sequential = crate::framework::Sequential;
+        let result = rx
+            .into_iter()
+            .par_bridge()
+            .map(|buf| sequential.execute(job, &buf))
+            .reduce(|| job.identity.clone(), |a, b| (job.reduce)(a, b));
+
+        reader.join().expect("reader thread panicked")?;
+        Ok(result)
+    })
+}
+
+/// Times every reader on a temporary file of `size` values, deleted again afterwards.
+pub fn print_streaming_report(size: usize, num_runs: usize) -> io::Result<()> {
+    let path = std::env::temp_dir().join(format!("map_reduce_stream_{}", size));
+    write_random_file(&path, size)?;
+    let job = crate::framework::sum_of_squares();
+
+    let time = |f: &dyn Fn() -> io::Result<f64>| -> io::Result<f64> {
+        // an untimed run surfaces read errors, the timed runs repeat the same reads
+        f()?;
+        Ok(crate::timing::average_secs(f, num_runs))
+    };
+
+    let mut timings = Vec::new();
+    for executor in crate::framework::all_executors() {
+        timings.push((
+            format!("mmap {}", executor.name()),
+            time(&|| stream_mmap(&path, &executor, &job))?,
+        ));
+    }
+    timings.push((
+        "buffered sequential".to_string(),
+        time(&|| stream_buffered(&path, DEFAULT_CHUNK, &crate::framework::Sequential, &job))?,
+    ));
+    timings.push((
+        "buffered rayon".to_string(),
+        time(&|| stream_buffered_rayon(&path, DEFAULT_CHUNK, &job))?,
+    ));
+    std::fs::remove_file(&path)?;
+
+    println!("MPRDC STREAMING");
+    println!("Measuring for {} elements read from disk", size);
+    for (name, avg) in &timings {
+        println!("Average {}: {}", name, avg);
+    }
+    Ok(())
+}
@@ -0,0 +1,318 @@
+//! Summation strategies for the sum of squares, usable from the sequential, rayon and SIMD
+//! backends alike.
+//!
+//! The backends associate the additions differently, so with naive summation every one of them
+//! gives a slightly different answer. The compensated and exact modes make that difference (and
+//! the price paid for removing it) measurable.
+#![allow(dead_code)]
+
+use rayon::prelude::*;
+
+use crate::simd;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Summation {
+    Naive,
+    /// recursive halving, error grows with log n instead of n
+    Pairwise,
+    Kahan,
+    /// Kahan that also survives adding a term larger than the running sum
+    Neumaier,
+    /// correctly rounded, squares split exactly with an FMA and summed with Shewchuk's partials
+    Exact,
+}
+
+impl Summation {
+    pub const ALL: [Summation; 5] = [
+        Summation::Naive,
+        Summation::Pairwise,
+        Summation::Kahan,
+        Summation::Neumaier,
+        Summation::Exact,
+    ];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    Sequential,
+    Rayon,
+    Simd,
+}
+
+impl Backend {
+    pub const ALL: [Backend; 3] = [Backend::Sequential, Backend::Rayon, Backend::Simd];
+}
+
+/// Running sum that can be split across threads/lanes and merged back.
+pub trait Accumulator: Default + Clone + Send {
+    fn add(&mut self, x: f64);
+    fn merge(&mut self, other: Self);
+    fn value(&self) -> f64;
+
+    /// Adds x * x. Only `ExactSum` cares about the rounding error of the square itself.
+    #[inline(always)]
+    fn add_square(&mut self, x: f64) {
+        self.add(x * x);
+    }
+}
+
+#[derive(Default, Clone)]
+pub struct NaiveSum(f64);
+
+#[derive(Default, Clone)]
+pub struct KahanSum {
+    sum: f64,
+    comp: f64,
+}
+
+#[derive(Default, Clone)]
+pub struct NeumaierSum {
+    sum: f64,
+    comp: f64,
+}
+
+/// Non-overlapping partials, same algorithm as Python's `math.fsum`.
+#[derive(Default, Clone)]
+pub struct ExactSum {
+    partials: Vec<f64>,
+}
+
+impl Accumulator for NaiveSum {
+    #[inline(always)]
+    fn add(&mut self, x: f64) {
+        self.0 += x;
+    }
+
+    fn merge(&mut self, other: Self) {
+        self.0 += other.0;
+    }
+
+    fn value(&self) -> f64 {
+        self.0
+    }
+}
+
+impl Accumulator for KahanSum {
+    #[inline(always)]
+    fn add(&mut self, x: f64) {
+        let y = x - self.comp;
+        let t = self.sum + y;
+        self.comp = (t - self.sum) - y;
+        self.sum = t;
+    }
+
+    fn merge(&mut self, other: Self) {
+        self.add(other.sum);
+        self.add(-other.comp);
+    }
+
+    fn value(&self) -> f64 {
+        self.sum
+    }
+}
+
+impl Accumulator for NeumaierSum {
+    #[inline(always)]
+    fn add(&mut self, x: f64) {
+        let t = self.sum + x;
+        if self.sum.abs() >= x.abs() {
+            self.comp += (self.sum - t) + x;
+        } else {
+            self.comp += (x - t) + self.sum;
+        }
+        self.sum = t;
+    }
+
+    fn merge(&mut self, other: Self) {
+        self.add(other.sum);
+        self.add(other.comp);
+    }
+
+    fn value(&self) -> f64 {
+        self.sum + self.comp
+    }
+}
+
+impl Accumulator for ExactSum {
+    fn add(&mut self, mut x: f64) {
+        let mut i = 0;
+        for j in 0..self.partials.len() {
+            let mut y = self.partials[j];
+            if x.abs() < y.abs() {
+                std::mem::swap(&mut x, &mut y);
+            }
+            let hi = x + y;
+            let lo = y - (hi - x);
+            if lo != 0. {
+                self.partials[i] = lo;
+                i += 1;
+            }
+            x = hi;
+        }
+        self.partials.truncate(i);
+        self.partials.push(x);
+    }
+
+    fn merge(&mut self, other: Self) {
+        for p in other.partials {
+            self.add(p);
+        }
+    }
+
+    fn value(&self) -> f64 {
+        // sum the partials from the top, with fsum's half-way correction for the final rounding
+        let Some((&top, rest)) = self.partials.split_last() else {
+            return 0.;
+        };
+        let mut hi = top;
+        let mut lo = 0.;
+        let mut i = rest.len();
+        while i > 0 {
+            i -= 1;
+            let x = hi;
+            let y = rest[i];
+            hi = x + y;
+            lo = y - (hi - x);
+            if lo != 0. {
+                break;
+            }
+        }
+        if i > 0 && ((lo < 0. && rest[i - 1] < 0.) || (lo > 0. && rest[i - 1] > 0.)) {
+            let y = lo * 2.;
+            let x = hi + y;
+            if y == x - hi {
+                hi = x;
+            }
+        }
+        hi
+    }
+
+    #[inline(always)]
+    fn add_square(&mut self, x: f64) {
+        let p = x * x;
+        self.add(p);
+        // x * x - p is exact thanks to the single rounding of the FMA
+        self.add(x.mul_add(x, -p));
+    }
+}
+
+const RAYON_CHUNK: usize = 4096;
+const LANES: usize = 4;
+const PAIRWISE_BLOCK: usize = 128;
+
+fn sequential<A: Accumulator>(data: &[f64]) -> A {
+    let mut acc = A::default();
+    for &x in data {
+        acc.add_square(x);
+    }
+    acc
+}
+
+fn rayon<A: Accumulator>(data: &[f64]) -> A {
+    data.par_chunks(RAYON_CHUNK)
+        .map(sequential::<A>)
+        .reduce(A::default, |mut a, b| {
+            a.merge(b);
+            a
+        })
+}
+
+/// One accumulator per lane, laid out so the compiler can keep all lanes in one vector register.
+fn lanes<A: Accumulator>(data: &[f64]) -> A {
+    let mut lanes: [A; LANES] = std::array::from_fn(|_| A::default());
+    let chunks = data.chunks_exact(LANES);
+    let rem = chunks.remainder();
+
+    for chunk in chunks {
+        for (lane, &x) in lanes.iter_mut().zip(chunk) {
+            lane.add_square(x);
+        }
+    }
+
+    let [mut acc, rest @ ..] = lanes;
+    for lane in rest {
+        acc.merge(lane);
+    }
+    for &x in rem {
+        acc.add_square(x);
+    }
+    acc
+}
+
+fn pairwise(data: &[f64], base: fn(&[f64]) -> f64) -> f64 {
+    if data.len() <= PAIRWISE_BLOCK {
+        return base(data);
+    }
+    let (l, r) = data.split_at(data.len() / 2);
+    pairwise(l, base) + pairwise(r, base)
+}
+
+fn pairwise_rayon(data: &[f64]) -> f64 {
+    if data.len() <= RAYON_CHUNK {
+        return pairwise(data, |d| sequential::<NaiveSum>(d).value());
+    }
+    let (l, r) = data.split_at(data.len() / 2);
+    let (l, r) = rayon::join(|| pairwise_rayon(l), || pairwise_rayon(r));
+    l + r
+}
+
+fn run<A: Accumulator>(data: &[f64], backend: Backend) -> f64 {
+    match backend {
+        Backend::Sequential => sequential::<A>(data).value(),
+        Backend::Rayon => rayon::<A>(data).value(),
+        Backend::Simd => lanes::<A>(data).value(),
+    }
+}
+
+/// Sum of squares of `data` with the given strategy on the given backend.
+pub fn sum_squares(data: &[f64], mode: Summation, backend: Backend) -> f64 {
+    match (mode, backend) {
+        // the naive SIMD case is the hand-written kernel, not the lane emulation
+        (Summation::Naive, Backend::Simd) => {
+            let (_, k) = simd::detect();
+            k(data)
+        }
+        (Summation::Naive, _) => run::<NaiveSum>(data, backend),
+        (Summation::Pairwise, Backend::Sequential) => {
+            pairwise(data, |d| sequential::<NaiveSum>(d).value())
+        }
+        (Summation::Pairwise, Backend::Rayon) => pairwise_rayon(data),
+        (Summation::Pairwise, Backend::Simd) => {
+            // detected once, not again in every leaf
+            let (_, k) = simd::detect();
+            pairwise(data, k)
+        }
+        (Summation::Kahan, _) => run::<KahanSum>(data, backend),
+        (Summation::Neumaier, _) => run::<NeumaierSum>(data, backend),
+        (Summation::Exact, _) => run::<ExactSum>(data, backend),
+    }
+}
+
+pub fn print_accuracy_report(sizes: &[usize], num_runs: usize) {
+    println!("MPRDC SUMMATION ACCURACY (error vs exact)");
+    println!(
+        "{:>10} {:>10} {:>12} {:>14} {:>14}",
+        "size", "mode", "backend", "secs", "rel_error"
+    );
+    for &size in sizes {
+        let data: Vec<f64> = (0..size).map(|_| fastrand::f64()).collect();
+        let exact = sum_squares(&data, Summation::Exact, Backend::Sequential);
+
+        for mode in Summation::ALL {
+            for backend in Backend::ALL {
+                let got = sum_squares(&data, mode, backend);
+                let secs =
+                    crate::timing::average_secs(|| sum_squares(&data, mode, backend), num_runs);
+
+                println!(
+                    "{:>10} {:>10} {:>12} {:>14.3e} {:>14.3e}",
+                    size,
+                    format!("{mode:?}"),
+                    format!("{backend:?}"),
+                    secs,
+                    ((got - exact) / exact).abs()
+                );
+            }
+        }
+    }
+}
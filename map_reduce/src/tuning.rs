@@ -0,0 +1,173 @@
+//! Chunk size x thread count sweep for the rayon sum of squares.
+//!
+//! rayon splits down to single items by default, which for `x * x` is far finer than it's worth.
+//! The chunked kernel below fixes the grain size, and the sweep runs it on explicitly sized
+//! thread pools to find the best configuration per input size and the size at which going
+//! parallel starts to pay off at all.
+#![allow(dead_code)]
+
+use rayon::prelude::*;
+
+use crate::timing::average_secs;
+
+/// How the input is cut into rayon jobs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Chunking {
+    /// `par_iter().with_min_len(n)`, rayon still decides where to split but never below n items
+    MinLen(usize),
+    /// `par_chunks(n)`, fixed chunks, each summed sequentially
+    ParChunks(usize),
+}
+
+/// `rayon_map_reduce` with a controlled grain size.
+pub fn rayon_map_reduce_chunked(data: &[f64], chunking: Chunking) -> f64 {
+    match chunking {
+        Chunking::MinLen(n) => data.par_iter().with_min_len(n.max(1)).map(|x| x * x).sum(),
+        Chunking::ParChunks(n) => data
+            .par_chunks(n.max(1))
+            .map(|chunk| chunk.iter().map(|x| x * x).sum::<f64>())
+            .sum(),
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SweepResult {
+    pub size: usize,
+    pub threads: usize,
+    pub chunking: Chunking,
+    pub secs: f64,
+}
+
+/// Best configuration for one input size, next to the sequential time.
+#[derive(Debug, Clone, Copy)]
+pub struct BestConfig {
+    pub best: SweepResult,
+    pub sequential_secs: f64,
+}
+
+impl BestConfig {
+    pub fn speedup(&self) -> f64 {
+        self.sequential_secs / self.best.secs
+    }
+}
+
+/// 1, 2, 4, ... up to the number of hardware threads, which is always included.
+pub fn default_thread_counts() -> Vec<usize> {
+    let max = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let mut counts: Vec<usize> = std::iter::successors(Some(1), |&t| Some(t * 2))
+        .take_while(|&t| t < max)
+        .collect();
+    counts.push(max);
+    counts
+}
+
+pub fn default_chunkings() -> Vec<Chunking> {
+    [256, 1024, 4096, 16384, 65536]
+        .iter()
+        .flat_map(|&n| [Chunking::MinLen(n), Chunking::ParChunks(n)])
+        .collect()
+}
+
+/// Times every chunking on every thread count for every size, `num_runs` runs each. Panics if
+/// `chunkings` or `thread_counts` is empty, there's no best configuration to report then.
+pub fn sweep(
+    sizes: &[usize],
+    chunkings: &[Chunking],
+    thread_counts: &[usize],
+    num_runs: usize,
+) -> Vec<(BestConfig, Vec<SweepResult>)> {
+    assert!(
+        !chunkings.is_empty() && !thread_counts.is_empty(),
+        "sweep needs at least one chunking and one thread count"
+    );
+    let pools: Vec<(usize, rayon::ThreadPool)> = thread_counts
+        .iter()
+        .map(|&t| {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(t)
+                .build()
+                .expect("failed to build thread pool");
+            (t, pool)
+        })
+        .collect();
+
+    sizes
+        .iter()
+        .map(|&size| {
+            let data: Vec<f64> = (0..size).map(|_| fastrand::f64()).collect();
+            let sequential_secs =
+                average_secs(|| data.iter().map(|x| x * x).sum::<f64>(), num_runs);
+
+            let mut results = Vec::with_capacity(pools.len() * chunkings.len());
+            for (threads, pool) in &pools {
+                for &chunking in chunkings {
+                    let secs = pool.install(|| {
+                        average_secs(|| rayon_map_reduce_chunked(&data, chunking), num_runs)
+                    });
+                    results.push(SweepResult {
+                        size,
+                        threads: *threads,
+                        chunking,
+                        secs,
+                    });
+                }
+            }
+
+            let best = *results
+                .iter()
+                .min_by(|a, b| a.secs.total_cmp(&b.secs))
+                .expect("checked non-empty above");
+            (
+                BestConfig {
+                    best,
+                    sequential_secs,
+                },
+                results,
+            )
+        })
+        .collect()
+}
+
+/// Smallest swept size at which some configuration with more than one thread beats the
+/// sequential loop. A one-thread pool is only a chunked sequential loop, so it doesn't count.
+pub fn crossover(swept: &[(BestConfig, Vec<SweepResult>)]) -> Option<usize> {
+    swept
+        .iter()
+        .find(|(best, results)| {
+            results
+                .iter()
+                .any(|r| r.threads > 1 && r.secs < best.sequential_secs)
+        })
+        .map(|(best, _)| best.best.size)
+}
+
+pub fn print_sweep_report(sizes: &[usize], num_runs: usize) {
+    let swept = sweep(
+        sizes,
+        &default_chunkings(),
+        &default_thread_counts(),
+        num_runs,
+    );
+    let best: Vec<BestConfig> = swept.iter().map(|(b, _)| *b).collect();
+
+    println!("MPRDC CHUNKING SWEEP");
+    println!(
+        "{:>10} {:>14} {:>8} {:>20} {:>14} {:>8}",
+        "size", "sequential", "threads", "chunking", "best", "speedup"
+    );
+    for b in &best {
+        println!(
+            "{:>10} {:>14.3e} {:>8} {:>20} {:>14.3e} {:>8.2}",
+            b.best.size,
+            b.sequential_secs,
+            b.best.threads,
+            format!("{:?}", b.best.chunking),
+            b.best.secs,
+            b.speedup()
+        );
+    }
+    match crossover(&swept) {
+        Some(size) => println!("Parallel first beats sequential at {} elements", size),
+        None => println!("Sequential beats every multi-threaded configuration at every size"),
+    }
+}
@@ -0,0 +1,71 @@
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+
+#[path = "../src/blas.rs"]
+mod blas;
+#[path = "../src/matrix.rs"]
+mod matrix;
+
+use blas::{Transpose, gemm, gemm_rayon, matmul_batched, matmul_batched_into};
+use matrix::Matrix;
+
+fn criterion_config() -> Criterion {
+    Criterion::default().sample_size(10)
+}
+
+fn random_matrix(size: usize) -> Matrix {
+    Matrix::try_from_collection(size, size, (0..size * size).map(|_| fastrand::f32()))
+        .expect("bad matrix size")
+}
+
+/// Repeated C = A * B, allocating a fresh output every time vs writing into the same buffer.
+fn gemm_bench(c: &mut Criterion) {
+    let mut group = c.benchmark_group("gemm-benches");
+
+    for &size in &[10, 100, 1000] {
+        let inp = (random_matrix(size), random_matrix(size));
+        let mut out = Matrix::zeros(size, size);
+        group.throughput(Throughput::Elements(2 * (size as u64).pow(3)));
+
+        group.bench_with_input(BenchmarkId::new("naive-alloc", size), &inp, |b, i| {
+            b.iter(|| i.0.matmul_naive(&i.1))
+        });
+        group.bench_with_input(BenchmarkId::new("gemm", size), &inp, |b, i| {
+            b.iter(|| gemm(1., &i.0, Transpose::No, &i.1, Transpose::No, 0., &mut out))
+        });
+        group.bench_with_input(BenchmarkId::new("rayon-alloc", size), &inp, |b, i| {
+            b.iter(|| i.0.matmul_rayon(&i.1))
+        });
+        group.bench_with_input(BenchmarkId::new("gemm-rayon", size), &inp, |b, i| {
+            b.iter(|| gemm_rayon(1., &i.0, Transpose::No, &i.1, Transpose::No, 0., &mut out))
+        });
+    }
+    group.finish();
+}
+
+fn batched_bench(c: &mut Criterion) {
+    let mut group = c.benchmark_group("batched-benches");
+
+    for &(batch, size) in &[(1000, 10), (100, 50), (10, 200)] {
+        let pairs: Vec<(Matrix, Matrix)> = (0..batch)
+            .map(|_| (random_matrix(size), random_matrix(size)))
+            .collect();
+        let mut outs: Vec<Matrix> = (0..batch).map(|_| Matrix::zeros(size, size)).collect();
+        let id = format!("{batch}x{size}");
+        group.throughput(Throughput::Elements(2 * batch * (size as u64).pow(3)));
+
+        group.bench_with_input(BenchmarkId::new("alloc", &id), &pairs, |b, p| {
+            b.iter(|| matmul_batched(p))
+        });
+        group.bench_with_input(BenchmarkId::new("into", &id), &pairs, |b, p| {
+            b.iter(|| matmul_batched_into(p, &mut outs))
+        });
+    }
+    group.finish();
+}
+
+criterion_group! {
+    name = benches;
+    config = criterion_config();
+    targets = gemm_bench, batched_bench
+}
+criterion_main!(benches);
@@ -0,0 +1,56 @@
+use criterion::{BatchSize, BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+
+#[path = "../src/matrix.rs"]
+mod matrix;
+
+use matrix::{Matrix, matmul_NdArray, matmul_ndarray_into};
+
+fn criterion_config() -> Criterion {
+    Criterion::default().sample_size(10)
+}
+
+/// The old entry point, conversion of both operands and the output allocation included.
+fn ndarray_owned_bench(c: &mut Criterion) {
+    let mut group = c.benchmark_group("ndarray-owned-benches");
+
+    for &size in &[10, 100, 1000, 5000] {
+        let m1: Matrix = Matrix::read_test_matrix(1, size).expect("matrix 1 read failed");
+        let m2: Matrix = Matrix::read_test_matrix(2, size).expect("matrix 2 read failed");
+
+        group.throughput(Throughput::Elements(2 * (size as u64).pow(3)));
+        let inp = (m1, m2);
+        group.bench_with_input(BenchmarkId::from_parameter(size), &inp, |b, i| {
+            b.iter_batched(
+                || (i.0.clone(), i.1.clone()),
+                |(m1, m2)| matmul_NdArray(m1, m2),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+/// Borrowed views into a preallocated output, only `general_mat_mul` itself is measured.
+fn ndarray_into_bench(c: &mut Criterion) {
+    let mut group = c.benchmark_group("ndarray-into-benches");
+
+    for &size in &[10, 100, 1000, 5000] {
+        let m1: Matrix = Matrix::read_test_matrix(1, size).expect("matrix 1 read failed");
+        let m2: Matrix = Matrix::read_test_matrix(2, size).expect("matrix 2 read failed");
+        let mut out = Matrix::zeros(size, size);
+
+        group.throughput(Throughput::Elements(2 * (size as u64).pow(3)));
+        let inp = (m1, m2);
+        group.bench_with_input(BenchmarkId::from_parameter(size), &inp, |b, i| {
+            b.iter(|| matmul_ndarray_into(&i.0, &i.1, &mut out))
+        });
+    }
+    group.finish();
+}
+
+criterion_group! {
+    name = benches;
+    config = criterion_config();
+    targets = ndarray_owned_bench, ndarray_into_bench
+}
+criterion_main!(benches);
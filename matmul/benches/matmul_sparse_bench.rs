@@ -0,0 +1,64 @@
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+
+#[path = "../src/matrix.rs"]
+mod matrix;
+#[path = "../src/sparse.rs"]
+mod sparse;
+
+use sparse::{CooMatrix, CsrMatrix};
+
+fn criterion_config() -> Criterion {
+    Criterion::default().sample_size(10)
+}
+
+fn read_pair(size: usize, density: f64) -> (CsrMatrix, CsrMatrix) {
+    let a = CooMatrix::read_test_sparse(1, size, density).expect("sparse matrix 1 read failed");
+    let b = CooMatrix::read_test_sparse(2, size, density).expect("sparse matrix 2 read failed");
+    (a.to_csr(), b.to_csr())
+}
+
+fn spmv_bench(c: &mut Criterion) {
+    let mut group = c.benchmark_group("spmv-benches");
+
+    for &size in &[100, 1000, 10000] {
+        for &density in &[0.001, 0.01, 0.1] {
+            let (a, _) = read_pair(size, density);
+            let x: Vec<f32> = (0..size).map(|_| fastrand::f32()).collect();
+            let id = format!("{size}/{density}");
+
+            group.bench_with_input(BenchmarkId::new("seq", &id), &(&a, &x), |b, i| {
+                b.iter(|| i.0.spmv(i.1))
+            });
+            group.bench_with_input(BenchmarkId::new("rayon", &id), &(&a, &x), |b, i| {
+                b.iter(|| i.0.spmv_rayon(i.1))
+            });
+        }
+    }
+    group.finish();
+}
+
+fn spgemm_bench(c: &mut Criterion) {
+    let mut group = c.benchmark_group("spgemm-benches");
+
+    for &size in &[100, 1000, 10000] {
+        for &density in &[0.001, 0.01] {
+            let inp = read_pair(size, density);
+            let id = format!("{size}/{density}");
+
+            group.bench_with_input(BenchmarkId::new("seq", &id), &inp, |b, i| {
+                b.iter(|| i.0.spgemm(&i.1))
+            });
+            group.bench_with_input(BenchmarkId::new("rayon", &id), &inp, |b, i| {
+                b.iter(|| i.0.spgemm_rayon(&i.1))
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group! {
+    name = benches;
+    config = criterion_config();
+    targets = spmv_bench, spgemm_bench
+}
+criterion_main!(benches);
@@ -0,0 +1,50 @@
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+
+#[path = "../src/matrix.rs"]
+mod matrix;
+
+use matrix::Matrix;
+
+type Kernel = fn(&Matrix, &Matrix) -> Matrix;
+
+/// Generated here rather than read from the Python inputs, so the bench runs on any machine.
+fn random_matrix(rng: &mut fastrand::Rng, size: usize) -> Matrix {
+    Matrix::try_from_collection(size, size, (0..size * size).map(|_| rng.f32()))
+        .expect("size * size values")
+}
+
+fn criterion_config() -> Criterion {
+    Criterion::default().sample_size(10)
+}
+
+/// Every loop structure once sequential and once on rayon, on the same inputs. Reading down a
+/// column compares access patterns, reading across compares parallel speedup.
+fn variants_bench(c: &mut Criterion) {
+    let kernels: [(&str, Kernel); 6] = [
+        ("naive", Matrix::matmul_naive),
+        ("transposed", Matrix::matmul_transposed),
+        ("recursive", Matrix::matmul_recursive),
+        ("rayon", Matrix::matmul_rayon),
+        ("transposed-rayon", Matrix::matmul_transposed_rayon),
+        ("recursive-rayon", Matrix::matmul_recursive_rayon),
+    ];
+
+    let mut rng = fastrand::Rng::with_seed(42);
+    for &size in &[100, 1000, 3000] {
+        let mut group = c.benchmark_group(format!("variants-{size}"));
+        let m1 = random_matrix(&mut rng, size);
+        let m2 = random_matrix(&mut rng, size);
+
+        group.throughput(Throughput::Elements(2 * (size as u64).pow(3)));
+        let inp = (m1, m2);
+        for (name, kernel) in kernels {
+            group.bench_with_input(BenchmarkId::from_parameter(name), &inp, |b, i| {
+                b.iter(|| kernel(&i.0, &i.1))
+            });
+        }
+        group.finish();
+    }
+}
+
+criterion_group! {name = benches; config = criterion_config(); targets = variants_bench}
+criterion_main!(benches);
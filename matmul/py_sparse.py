@@ -0,0 +1,36 @@
+import numpy as np
+import scipy.sparse as sp
+import timeit
+
+
+def read_sparse(ident, size, density):
+    path = f"/home/aperiax/School/SVK/sparse_{ident}_{size}_{density}"
+    with open(path, "r") as f:
+        rows, cols = (int(x) for x in f.readline().split())
+    triplets = np.loadtxt(path, skiprows=1, ndmin=2)
+    return sp.coo_matrix(
+        (triplets[:, 2].astype(np.float32), (triplets[:, 0].astype(np.int64), triplets[:, 1].astype(np.int64))),
+        shape=(rows, cols),
+    ).tocsr()
+
+
+if __name__ == "__main__":
+    num_runs = 10
+    sizes = [100, 1000, 10000]
+    densities = [0.001, 0.01, 0.1]
+    res_spmv = []
+    res_spgemm = []
+
+    for size in sizes:
+        for density in densities:
+            A = read_sparse(1, size, density)
+            B = read_sparse(2, size, density)
+            x = np.random.random(size).astype(np.float32)
+
+            res_spmv.append(timeit.timeit(lambda: A @ x, number=num_runs) / num_runs)
+            res_spgemm.append(timeit.timeit(lambda: A @ B, number=num_runs) / num_runs)
+
+    print("SPARSE MATMUL (scipy.sparse)")
+    print(f"Tested sizes: {sizes}, densities: {densities}")
+    print(f"Averages spmv, {num_runs} runs: {res_spmv}")
+    print(f"Averages spgemm, {num_runs} runs: {res_spgemm}")
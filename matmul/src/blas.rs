@@ -0,0 +1,286 @@
+//! BLAS-like entry points on top of `Matrix`: gemv, in-place gemm with transposes, and a
+//! batched matmul. Everything here writes into caller-owned output, so repeated calls don't
+//! allocate (the same thing numpy's `out=` buys on the Python side).
+#![allow(dead_code)]
+
+use rayon::prelude::*;
+
+use crate::matrix::Matrix;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transpose {
+    No,
+    Yes,
+}
+
+impl Transpose {
+    /// Dimensions of op(m).
+    fn dims(self, m: &Matrix) -> (usize, usize) {
+        match self {
+            Transpose::No => (m.rows, m.cols),
+            Transpose::Yes => (m.cols, m.rows),
+        }
+    }
+
+    /// op(m)[r, c]
+    #[inline(always)]
+    fn at(self, m: &Matrix, r: usize, c: usize) -> f32 {
+        match self {
+            Transpose::No => m.data[r * m.cols + c],
+            Transpose::Yes => m.data[c * m.cols + r],
+        }
+    }
+}
+
+/// y = alpha * op(a) * x + beta * y
+pub fn gemv(alpha: f32, a: &Matrix, trans: Transpose, x: &[f32], beta: f32, y: &mut [f32]) {
+    let (m, k) = trans.dims(a);
+    assert!(k == x.len(), "Dimension mismatch for gemv");
+    assert!(m == y.len(), "Output vector has the wrong length");
+
+    for (r, out) in y.iter_mut().enumerate() {
+        let mut acc = 0_f32;
+        for (i, &xi) in x.iter().enumerate() {
+            acc += trans.at(a, r, i) * xi;
+        }
+        *out = scale_into(alpha * acc, beta, *out);
+    }
+}
+
+/// beta == 0 overwrites, so garbage (or NaN) in an uninitialized output doesn't leak through,
+/// same convention as reference BLAS.
+#[inline(always)]
+fn scale_into(value: f32, beta: f32, old: f32) -> f32 {
+    if beta == 0. {
+        value
+    } else {
+        value + beta * old
+    }
+}
+
+/// One row of C in i-k-j order, so the non-transposed B is read contiguously.
+#[inline(always)]
+#[allow(clippy::too_many_arguments)]
+fn gemm_row(
+    alpha: f32,
+    a: &Matrix,
+    trans_a: Transpose,
+    b: &Matrix,
+    trans_b: Transpose,
+    beta: f32,
+    r: usize,
+    c_row: &mut [f32],
+) {
+    let k = trans_a.dims(a).1;
+
+    for v in c_row.iter_mut() {
+        *v = scale_into(0., beta, *v);
+    }
+    for i in 0..k {
+        let a_ri = alpha * trans_a.at(a, r, i);
+        match trans_b {
+            Transpose::No => {
+                let b_row = &b.data[i * b.cols..(i + 1) * b.cols];
+                for (v, &b_ij) in c_row.iter_mut().zip(b_row) {
+                    *v += a_ri * b_ij;
+                }
+            }
+            Transpose::Yes => {
+                for (j, v) in c_row.iter_mut().enumerate() {
+                    *v += a_ri * b.data[j * b.cols + i];
+                }
+            }
+        }
+    }
+}
+
+fn check_gemm_dims(a: &Matrix, trans_a: Transpose, b: &Matrix, trans_b: Transpose, c: &Matrix) {
+    let (m, k) = trans_a.dims(a);
+    let (k_b, n) = trans_b.dims(b);
+    assert!(k == k_b, "Dimension mismatch for gemm");
+    assert!(
+        c.rows == m && c.cols == n && c.data.len() == m * n,
+        "Output matrix has the wrong dimensions"
+    );
+}
+
+/// C = alpha * op(A) * op(B) + beta * C, in place.
+pub fn gemm(
+    alpha: f32,
+    a: &Matrix,
+    trans_a: Transpose,
+    b: &Matrix,
+    trans_b: Transpose,
+    beta: f32,
+    c: &mut Matrix,
+) {
+    check_gemm_dims(a, trans_a, b, trans_b, c);
+
+    let n = c.cols;
+    for (r, c_row) in c.data.chunks_mut(n.max(1)).enumerate() {
+        gemm_row(alpha, a, trans_a, b, trans_b, beta, r, c_row);
+    }
+}
+
+/// Same as `gemm`, rows of C are split across the rayon pool.
+pub fn gemm_rayon(
+    alpha: f32,
+    a: &Matrix,
+    trans_a: Transpose,
+    b: &Matrix,
+    trans_b: Transpose,
+    beta: f32,
+    c: &mut Matrix,
+) {
+    check_gemm_dims(a, trans_a, b, trans_b, c);
+
+    let n = c.cols;
+    c.data
+        .par_chunks_mut(n.max(1))
+        .enumerate()
+        .for_each(|(r, c_row)| gemm_row(alpha, a, trans_a, b, trans_b, beta, r, c_row));
+}
+
+/// Multiplies every pair, parallel across the batch. Each product itself is sequential, which
+/// is what you want for many small matrices.
+pub fn matmul_batched(pairs: &[(Matrix, Matrix)]) -> Vec<Matrix> {
+    pairs.par_iter().map(|(a, b)| a.matmul_naive(b)).collect()
+}
+
+/// Allocation-free version of `matmul_batched`, `outs[i] = pairs[i].0 * pairs[i].1`.
+pub fn matmul_batched_into(pairs: &[(Matrix, Matrix)], outs: &mut [Matrix]) {
+    assert!(pairs.len() == outs.len(), "Batch and output sizes differ");
+
+    pairs
+        .par_iter()
+        .zip(outs.par_iter_mut())
+        .for_each(|((a, b), c)| gemm(1., a, Transpose::No, b, Transpose::No, 0., c));
+}
+
+#[cfg(test)]
+mod tests {
+    // the benches pull this file in with cfg(test) but without the test harness, so everything
+    // the tests need is imported inside them
+
+    fn random(rows: usize, cols: usize, rng: &mut fastrand::Rng) -> super::Matrix {
+        let data = (0..rows * cols).map(|_| rng.f32() * 2. - 1.);
+        super::Matrix::try_from_collection(cols, rows, data).unwrap()
+    }
+
+    fn transpose(m: &super::Matrix) -> super::Matrix {
+        let data = (0..m.rows * m.cols).map(|i| m.data[(i % m.rows) * m.cols + i / m.rows]);
+        super::Matrix::try_from_collection(m.rows, m.cols, data).unwrap()
+    }
+
+    /// alpha * a * b + beta * c through the naive kernel.
+    fn reference(
+        alpha: f32,
+        a: &super::Matrix,
+        b: &super::Matrix,
+        beta: f32,
+        c: &[f32],
+    ) -> Vec<f32> {
+        let product = a.matmul_naive(b).data;
+        product
+            .iter()
+            .zip(c)
+            .map(|(p, c)| alpha * p + beta * c)
+            .collect()
+    }
+
+    fn assert_close(got: &[f32], expected: &[f32], what: &str) {
+        assert_eq!(got.len(), expected.len(), "{what}: output sizes differ");
+        for (g, e) in got.iter().zip(expected) {
+            assert!(
+                (g - e).abs() <= 1e-5 * e.abs().max(1.),
+                "{what}: {g} vs {e}"
+            );
+        }
+    }
+
+    #[test]
+    fn gemm_transposes_and_scaling() {
+        use super::*;
+
+        let mut rng = fastrand::Rng::with_seed(5);
+        // stored as A^T (5x3) and B^T (4x5), so op(A) * op(B) is 3x4
+        let at = random(5, 3, &mut rng);
+        let bt = random(4, 5, &mut rng);
+        let c0 = random(3, 4, &mut rng);
+        let expected = reference(2., &transpose(&at), &transpose(&bt), -0.5, &c0.data);
+
+        let mut c = c0.clone();
+        gemm(2., &at, Transpose::Yes, &bt, Transpose::Yes, -0.5, &mut c);
+        assert_close(&c.data, &expected, "gemm");
+
+        let mut c = c0.clone();
+        gemm_rayon(2., &at, Transpose::Yes, &bt, Transpose::Yes, -0.5, &mut c);
+        assert_close(&c.data, &expected, "gemm_rayon");
+
+        // beta = 0 has to overwrite whatever was in y
+        let x = random(5, 1, &mut rng);
+        let mut y = vec![f32::NAN; 3];
+        gemv(1., &at, Transpose::Yes, &x.data, 0., &mut y);
+        let expected = reference(1., &transpose(&at), &x, 0., &[0.; 3]);
+        assert_close(&y, &expected, "gemv");
+    }
+
+    #[test]
+    fn gemm_every_transpose_combination() {
+        use super::*;
+
+        let mut rng = fastrand::Rng::with_seed(7);
+        // op(A) is 3x5, op(B) is 5x4, stored transposed where asked
+        let (m, k, n) = (3, 5, 4);
+        for trans_a in [Transpose::No, Transpose::Yes] {
+            for trans_b in [Transpose::No, Transpose::Yes] {
+                let op_a = random(m, k, &mut rng);
+                let op_b = random(k, n, &mut rng);
+                let stored = |op: &Matrix, trans| match trans {
+                    Transpose::No => op.clone(),
+                    Transpose::Yes => transpose(op),
+                };
+                let (a, b) = (stored(&op_a, trans_a), stored(&op_b, trans_b));
+                let c0 = random(m, n, &mut rng);
+                let expected = reference(1.5, &op_a, &op_b, 2., &c0.data);
+                let what = format!("{trans_a:?} {trans_b:?}");
+
+                let mut c = c0.clone();
+                gemm(1.5, &a, trans_a, &b, trans_b, 2., &mut c);
+                assert_close(&c.data, &expected, &what);
+
+                let mut c = c0.clone();
+                gemm_rayon(1.5, &a, trans_a, &b, trans_b, 2., &mut c);
+                assert_close(&c.data, &expected, &what);
+            }
+        }
+    }
+
+    #[test]
+    fn batched_matmul_matches_naive() {
+        use super::*;
+
+        let mut rng = fastrand::Rng::with_seed(8);
+        let shapes = [(1, 1, 1), (3, 5, 4), (7, 2, 6), (16, 16, 16)];
+        let pairs: Vec<(Matrix, Matrix)> = shapes
+            .iter()
+            .map(|&(m, k, n)| (random(m, k, &mut rng), random(k, n, &mut rng)))
+            .collect();
+        // garbage in the outputs, beta = 0 has to overwrite it
+        let mut outs: Vec<Matrix> = shapes
+            .iter()
+            .map(|&(m, _, n)| Matrix::try_from_collection(n, m, vec![f32::NAN; m * n]).unwrap())
+            .collect();
+
+        let batched = matmul_batched(&pairs);
+        matmul_batched_into(&pairs, &mut outs);
+        assert_eq!(batched.len(), pairs.len());
+        for (((a, b), c), out) in pairs.iter().zip(&batched).zip(&outs) {
+            let expected = a.matmul_naive(b).data;
+            assert_eq!((c.rows, c.cols), (a.rows, b.cols));
+            assert_eq!((out.rows, out.cols), (a.rows, b.cols));
+            assert_close(&c.data, &expected, "matmul_batched");
+            assert_close(&out.data, &expected, "matmul_batched_into");
+        }
+    }
+}
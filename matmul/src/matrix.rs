@@ -0,0 +1,547 @@
+//! The dense `Matrix` and its kernels. Shared with the benches through `#[path]`, so not every
+//! target uses every item.
+#![allow(dead_code)]
+
+use ndarray::linalg::general_mat_mul;
+use ndarray::{Array2, ArrayView2, ArrayViewMut2, Axis, ShapeError};
+use rayon::iter::{
+    IndexedParallelIterator, IntoParallelIterator, ParallelExtend, ParallelIterator,
+};
+use rayon::slice::ParallelSliceMut;
+use std::io::BufRead;
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Write},
+};
+
+/// How the inner dot product of a kernel accumulates its partial sums.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Accumulation {
+    /// plain `acc += a * b`, what `matmul_naive` and `matmul_rayon` do
+    Plain,
+    /// Kahan compensated summation, carries the lost low-order bits along
+    Kahan,
+}
+
+impl Accumulation {
+    #[inline(always)]
+    fn dot(self, lhs: &Matrix, rhs: &Matrix, r: usize, c: usize) -> f32 {
+        match self {
+            Accumulation::Plain => {
+                let mut acc = 0_f32;
+                for i in 0..lhs.cols {
+                    acc += lhs.data[r * lhs.cols + i] * rhs.data[i * rhs.cols + c];
+                }
+                acc
+            }
+            Accumulation::Kahan => {
+                let mut acc = 0_f32;
+                let mut comp = 0_f32;
+                for i in 0..lhs.cols {
+                    let y = lhs.data[r * lhs.cols + i] * rhs.data[i * rhs.cols + c] - comp;
+                    let t = acc + y;
+                    comp = (t - acc) - y;
+                    acc = t;
+                }
+                acc
+            }
+        }
+    }
+}
+
+// matrix is basically just a vector of vectors!
+#[derive(Clone)]
+pub struct Matrix {
+    pub rows: usize,
+    pub cols: usize,
+    pub data: Vec<f32>,
+}
+
+impl Matrix {
+    /// Prepare an empty matrix
+    pub fn new_empty(nrows: usize, ncols: usize) -> Self {
+        let width: usize = nrows * ncols;
+        let data: Vec<f32> = Vec::with_capacity(width);
+
+        Matrix {
+            rows: nrows,
+            cols: ncols,
+            data,
+        }
+    }
+
+    /// Matrix of zeros, unlike `new_empty` the data is already initialized
+    pub fn zeros(nrows: usize, ncols: usize) -> Self {
+        Matrix {
+            rows: nrows,
+            cols: ncols,
+            data: vec![0_f32; nrows * ncols],
+        }
+    }
+
+    /// Borrows the data as an ndarray view, no copy.
+    pub fn as_array_view(&self) -> ArrayView2<'_, f32> {
+        ArrayView2::from_shape((self.rows, self.cols), &self.data)
+            .expect("matrix data does not match its dimensions")
+    }
+
+    pub fn as_array_view_mut(&mut self) -> ArrayViewMut2<'_, f32> {
+        ArrayViewMut2::from_shape((self.rows, self.cols), &mut self.data)
+            .expect("matrix data does not match its dimensions")
+    }
+
+    /// processes the collection into a square matrix for demonstration purposes
+    pub fn try_from_collection<T>(ncols: usize, nrows: usize, collection: T) -> Result<Self, String>
+    where
+        T: IntoIterator<Item = f32>,
+    {
+        let data: Vec<f32> = collection.into_iter().collect();
+        if nrows * ncols != data.len() {
+            return Err(
+                "Wrong combination of columns/rows to convert collection into matrix".to_string(),
+            );
+        }
+
+        Ok(Matrix {
+            rows: nrows,
+            cols: ncols,
+            data,
+        })
+    }
+
+    pub fn try_from_array<const N: usize>(
+        ncols: usize,
+        nrows: usize,
+        arr: [f32; N],
+    ) -> Result<Self, String> {
+        if nrows * ncols != N {
+            return Err(
+                "Wrong combination of columns/rows to convert collection into matrix".to_string(),
+            );
+        };
+
+        let data = arr.into_iter().collect::<Vec<f32>>();
+
+        Ok(Matrix {
+            rows: nrows,
+            cols: ncols,
+            data,
+        })
+    }
+
+    pub fn matmul_naive(&self, other: &Matrix) -> Matrix {
+        // c_ij = sum_{k=1}^n a_ik * b_ik
+        // access indices in row-major order
+
+        assert!(self.cols == other.rows, "Dimension mismatch for matmul");
+
+        let mut tmp_: Vec<f32> = Vec::with_capacity(self.rows * self.cols);
+        // the second matrix has to also be row-major, just like the first one
+        // well it doesn't really matter for it to be row-major really.
+
+        for r in 0..self.rows {
+            for c in 0..other.cols {
+                let mut acc = 0_f32;
+                for i in 0..self.cols {
+                    let a = self.data[r * self.cols + i];
+                    let b = other.data[i * other.cols + c];
+
+                    acc += a * b;
+                }
+                tmp_.push(acc);
+            }
+        }
+
+        Matrix {
+            rows: self.rows,
+            cols: other.cols,
+            data: tmp_,
+        }
+    }
+
+    pub fn matmul_rayon(&self, other: &Matrix) -> Matrix {
+        // I'm not outgunning BLAS/LAPAC, but i can get kind of close-ish without much hassle
+        // for medium-sized matrices.
+        let mut tmp_: Vec<f32> = Vec::with_capacity(self.cols * other.rows);
+
+        tmp_.par_extend((0..self.rows).into_par_iter().flat_map(|r| {
+            (0..other.cols).into_par_iter().map(move |c| {
+                let mut acc: f32 = 0.;
+                for i in 0..self.cols {
+                    let a = self.data[r * self.cols + i];
+                    let b = other.data[i * other.cols + c];
+
+                    acc += a * b
+                }
+                acc
+            })
+        }));
+
+        Matrix {
+            rows: self.rows,
+            cols: other.cols,
+            data: tmp_,
+        }
+    }
+
+    /// Same loop structure as `matmul_naive`, with a selectable accumulation mode.
+    pub fn matmul_naive_with(&self, other: &Matrix, mode: Accumulation) -> Matrix {
+        assert!(self.cols == other.rows, "Dimension mismatch for matmul");
+
+        let mut tmp_: Vec<f32> = Vec::with_capacity(self.rows * other.cols);
+        for r in 0..self.rows {
+            for c in 0..other.cols {
+                tmp_.push(mode.dot(self, other, r, c));
+            }
+        }
+
+        Matrix {
+            rows: self.rows,
+            cols: other.cols,
+            data: tmp_,
+        }
+    }
+
+    /// Row-major copy of the transpose.
+    pub fn transposed(&self) -> Matrix {
+        let mut data: Vec<f32> = Vec::with_capacity(self.rows * self.cols);
+        for c in 0..self.cols {
+            for r in 0..self.rows {
+                data.push(self.data[r * self.cols + c]);
+            }
+        }
+
+        Matrix {
+            rows: self.cols,
+            cols: self.rows,
+            data,
+        }
+    }
+
+    /// Naive loop order, but `other` is transposed once up front so both operands of every dot
+    /// product are walked row-contiguously instead of striding down a column of `other`.
+    pub fn matmul_transposed(&self, other: &Matrix) -> Matrix {
+        assert!(self.cols == other.rows, "Dimension mismatch for matmul");
+
+        let other_t = other.transposed();
+        let mut tmp_: Vec<f32> = Vec::with_capacity(self.rows * other.cols);
+        for r in 0..self.rows {
+            let lhs = &self.data[r * self.cols..(r + 1) * self.cols];
+            for c in 0..other.cols {
+                let rhs = &other_t.data[c * self.cols..(c + 1) * self.cols];
+                tmp_.push(lhs.iter().zip(rhs).map(|(a, b)| a * b).sum());
+            }
+        }
+
+        Matrix {
+            rows: self.rows,
+            cols: other.cols,
+            data: tmp_,
+        }
+    }
+
+    /// `matmul_transposed` with the output rows split across the rayon pool.
+    pub fn matmul_transposed_rayon(&self, other: &Matrix) -> Matrix {
+        assert!(self.cols == other.rows, "Dimension mismatch for matmul");
+
+        let other_t = other.transposed();
+        let mut out = Matrix::zeros(self.rows, other.cols);
+        out.data
+            .par_chunks_mut(other.cols.max(1))
+            .enumerate()
+            .for_each(|(r, out_row)| {
+                let lhs = &self.data[r * self.cols..(r + 1) * self.cols];
+                for (c, v) in out_row.iter_mut().enumerate() {
+                    let rhs = &other_t.data[c * self.cols..(c + 1) * self.cols];
+                    *v = lhs.iter().zip(rhs).map(|(a, b)| a * b).sum();
+                }
+            });
+        out
+    }
+
+    /// Cache-oblivious divide and conquer, see `recursive_kernel`.
+    pub fn matmul_recursive(&self, other: &Matrix) -> Matrix {
+        assert!(self.cols == other.rows, "Dimension mismatch for matmul");
+
+        let mut out = Matrix::zeros(self.rows, other.cols);
+        recursive_kernel(
+            self.as_array_view(),
+            other.as_array_view(),
+            out.as_array_view_mut(),
+            false,
+        );
+        out
+    }
+
+    /// `matmul_recursive` with independent halves handed to `rayon::join`.
+    pub fn matmul_recursive_rayon(&self, other: &Matrix) -> Matrix {
+        assert!(self.cols == other.rows, "Dimension mismatch for matmul");
+
+        let mut out = Matrix::zeros(self.rows, other.cols);
+        recursive_kernel(
+            self.as_array_view(),
+            other.as_array_view(),
+            out.as_array_view_mut(),
+            true,
+        );
+        out
+    }
+
+    /// Same parallel split as `matmul_rayon`, with a selectable accumulation mode.
+    pub fn matmul_rayon_with(&self, other: &Matrix, mode: Accumulation) -> Matrix {
+        assert!(self.cols == other.rows, "Dimension mismatch for matmul");
+
+        let mut tmp_: Vec<f32> = Vec::with_capacity(self.rows * other.cols);
+        tmp_.par_extend((0..self.rows).into_par_iter().flat_map(|r| {
+            (0..other.cols)
+                .into_par_iter()
+                .map(move |c| mode.dot(self, other, r, c))
+        }));
+
+        Matrix {
+            rows: self.rows,
+            cols: other.cols,
+            data: tmp_,
+        }
+    }
+
+    pub fn output_matrix_for_python() -> std::io::Result<()> {
+        for &ident in &[1, 2] {
+            for &size in &[10, 100, 1000, 3000, 5000] {
+                let data_1: Vec<f32> = (0..size * size).map(|_| fastrand::f32()).collect();
+                let file = File::create(format!(
+                    "/home/aperiax/School/SVK/matrix_{}_{}",
+                    ident, size
+                ))?;
+                let mut writer = BufWriter::new(file);
+
+                for a in data_1 {
+                    writeln!(writer, "{}", a)?;
+                }
+                writer.flush()?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn read_test_matrix(ident: usize, size: usize) -> io::Result<Matrix> {
+        Matrix::read_matrix_file(
+            &format!("/home/aperiax/School/SVK/matrix_{}_{}", ident, size),
+            size,
+        )
+    }
+
+    /// A `size` x `size` matrix written one value per line, as `output_matrix_for_python` does.
+    pub fn read_matrix_file(path: &str, size: usize) -> io::Result<Matrix> {
+        let file = File::open(path)?;
+        let reader = BufReader::new(file);
+        let mut data: Vec<f32> = Vec::with_capacity(size * size);
+        let invalid =
+            |e: String| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path, e));
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            let mut parts = line.split_whitespace();
+            let a: f32 = parts
+                .next()
+                .and_then(|p| p.parse().ok())
+                .ok_or_else(|| invalid(format!("line {} is not a number", i + 1)))?;
+            data.push(a);
+        }
+        Matrix::try_from_collection(size, size, data).map_err(invalid)
+    }
+}
+
+/// Largest dimension at which `recursive_kernel` stops splitting. 64^2 f32 per operand keeps all
+/// three blocks in L1/L2 on anything recent.
+pub const RECURSION_CUTOFF: usize = 64;
+
+/// c += a * b. Halves the largest of m, n and k until everything is below `RECURSION_CUTOFF`, so
+/// at some depth the blocks fit whatever cache there is without knowing its size. Splitting m or
+/// n gives disjoint halves of c which may run in parallel, halves of k both write all of c and
+/// run one after another.
+fn recursive_kernel(
+    a: ArrayView2<'_, f32>,
+    b: ArrayView2<'_, f32>,
+    mut c: ArrayViewMut2<'_, f32>,
+    parallel: bool,
+) {
+    let (m, k) = a.dim();
+    let n = b.dim().1;
+
+    if m.max(n).max(k) <= RECURSION_CUTOFF {
+        for i in 0..m {
+            let mut c_row = c.row_mut(i);
+            for p in 0..k {
+                let a_ip = a[[i, p]];
+                for (cv, &bv) in c_row.iter_mut().zip(b.row(p).iter()) {
+                    *cv += a_ip * bv;
+                }
+            }
+        }
+        return;
+    }
+
+    if m >= n && m >= k {
+        let (a1, a2) = a.split_at(Axis(0), m / 2);
+        let (c1, c2) = c.split_at(Axis(0), m / 2);
+        fork(
+            parallel,
+            move || recursive_kernel(a1, b, c1, parallel),
+            move || recursive_kernel(a2, b, c2, parallel),
+        );
+    } else if n >= k {
+        let (b1, b2) = b.split_at(Axis(1), n / 2);
+        let (c1, c2) = c.split_at(Axis(1), n / 2);
+        fork(
+            parallel,
+            move || recursive_kernel(a, b1, c1, parallel),
+            move || recursive_kernel(a, b2, c2, parallel),
+        );
+    } else {
+        let (a1, a2) = a.split_at(Axis(1), k / 2);
+        let (b1, b2) = b.split_at(Axis(0), k / 2);
+        recursive_kernel(a1, b1, c.view_mut(), parallel);
+        recursive_kernel(a2, b2, c, parallel);
+    }
+}
+
+#[inline(always)]
+fn fork(parallel: bool, left: impl FnOnce() + Send, right: impl FnOnce() + Send) {
+    if parallel {
+        rayon::join(left, right);
+    } else {
+        left();
+        right();
+    }
+}
+
+impl From<Array2<f32>> for Matrix {
+    fn from(arr: Array2<f32>) -> Self {
+        let (rows, cols) = arr.dim();
+        if !arr.is_standard_layout() {
+            // column-major or strided, has to be copied into row-major order anyway
+            return Matrix {
+                rows,
+                cols,
+                data: arr.iter().copied().collect(),
+            };
+        }
+
+        let (mut data, offset) = arr.into_raw_vec_and_offset();
+        // an owned array that was sliced in place keeps its whole buffer around
+        let start = offset.unwrap_or(0);
+        data.truncate(start + rows * cols);
+        data.drain(..start);
+
+        Matrix { rows, cols, data }
+    }
+}
+
+impl TryFrom<Matrix> for Array2<f32> {
+    type Error = ShapeError;
+
+    fn try_from(m: Matrix) -> Result<Self, Self::Error> {
+        Array2::from_shape_vec((m.rows, m.cols), m.data)
+    }
+}
+
+impl<'a> TryFrom<&'a Matrix> for ArrayView2<'a, f32> {
+    type Error = ShapeError;
+
+    fn try_from(m: &'a Matrix) -> Result<Self, Self::Error> {
+        ArrayView2::from_shape((m.rows, m.cols), &m.data)
+    }
+}
+
+#[allow(non_snake_case)]
+pub fn matmul_NdArray(m1: Matrix, m2: Matrix) -> Array2<f32> {
+    // cast the two matrices into the ndarray counterpart first
+    let first = Array2::try_from(m1).unwrap();
+    let second = Array2::try_from(m2).unwrap();
+
+    first.dot(&second)
+}
+
+/// out = a * b through ndarray's `general_mat_mul`, borrowing everything. Nothing is converted or
+/// allocated, so this is the kernel to time when comparing against ndarray/BLAS itself.
+pub fn matmul_ndarray_into(a: &Matrix, b: &Matrix, out: &mut Matrix) {
+    assert!(a.cols == b.rows, "Dimension mismatch for matmul");
+    assert!(
+        out.rows == a.rows && out.cols == b.cols,
+        "Output matrix has the wrong dimensions"
+    );
+
+    general_mat_mul(
+        1.,
+        &a.as_array_view(),
+        &b.as_array_view(),
+        0.,
+        &mut out.as_array_view_mut(),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    // the benches pull this file in with cfg(test) but without the test harness, so everything
+    // the tests need is imported inside them
+
+    #[test]
+    fn matrix_file_uses_size_for_dimensions() {
+        use super::*;
+
+        let size = 7;
+        let path = std::env::temp_dir().join(format!("matmul_test_{}", std::process::id()));
+        let path = path.to_str().unwrap();
+        let values: Vec<String> = (0..size * size).map(|i| i.to_string()).collect();
+        std::fs::write(path, values.join("\n")).unwrap();
+
+        let m = Matrix::read_matrix_file(path, size);
+        let short = Matrix::read_matrix_file(path, size + 1);
+        std::fs::remove_file(path).unwrap();
+        let m = m.unwrap();
+        assert_eq!((m.rows, m.cols), (size, size));
+        assert_eq!(m.data[size + 1], (size + 1) as f32);
+        assert!(short.is_err_and(|e| e.kind() == std::io::ErrorKind::InvalidData));
+
+        std::fs::write(path, "1\n2\nx\n").unwrap();
+        let garbage = Matrix::read_matrix_file(path, 1);
+        std::fs::remove_file(path).unwrap();
+        assert!(garbage.is_err_and(|e| e.kind() == std::io::ErrorKind::InvalidData));
+    }
+
+    #[test]
+    fn ndarray_conversions() {
+        use super::*;
+        use ndarray::s;
+
+        let arr = Array2::from_shape_vec((4, 3), (0..12).map(|v| v as f32).collect()).unwrap();
+
+        // standard layout, but the buffer starts before the first element
+        let rows = arr.clone().slice_move(s![1..3, ..]);
+        let m = Matrix::from(rows);
+        assert_eq!((m.rows, m.cols), (2, 3));
+        assert_eq!(m.data, [3., 4., 5., 6., 7., 8.]);
+
+        // column-major and strided both go through the copy
+        let m = Matrix::from(arr.clone().reversed_axes());
+        assert_eq!((m.rows, m.cols), (3, 4));
+        assert_eq!(m.data, [0., 3., 6., 9., 1., 4., 7., 10., 2., 5., 8., 11.]);
+        let m = Matrix::from(arr.clone().slice_move(s![.., 1..]));
+        assert_eq!((m.rows, m.cols), (4, 2));
+        assert_eq!(m.data, [1., 2., 4., 5., 7., 8., 10., 11.]);
+
+        let m = Matrix::from(arr.clone());
+        let view = ArrayView2::try_from(&m).unwrap();
+        assert_eq!(view, arr);
+        assert_eq!(Array2::try_from(m).unwrap(), arr);
+
+        // data that doesn't match the dimensions is an error, not a panic
+        let bad = Matrix {
+            rows: 2,
+            cols: 2,
+            data: vec![1., 2., 3.],
+        };
+        assert!(ArrayView2::try_from(&bad).is_err());
+        assert!(Array2::try_from(bad).is_err());
+    }
+}
@@ -0,0 +1,99 @@
+//! Throughput numbers for the matmul kernels.
+//!
+//! Seconds alone hide how far an O(n^3) kernel is from peak, so the one-shot report prints
+//! achieved GFLOP/s next to a rough arithmetic intensity (flops per byte of memory traffic)
+//! per kernel. With the machine's peak FLOP/s and bandwidth that is enough to place every
+//! kernel on a roofline plot.
+use crate::average_secs;
+use crate::matrix::Matrix;
+use crate::verify::KERNELS;
+
+const F32_BYTES: f64 = std::mem::size_of::<f32>() as f64;
+
+/// Floating point operations of an (m x k) * (k x n) product, one mul and one add per term.
+pub fn flops(m: usize, n: usize, k: usize) -> f64 {
+    2. * m as f64 * n as f64 * k as f64
+}
+
+pub fn gflops(m: usize, n: usize, k: usize, secs: f64) -> f64 {
+    flops(m, n, k) / secs / 1e9
+}
+
+/// How much memory traffic a kernel's loop structure causes, assuming anything that is reused
+/// within one tile stays in cache and nothing else does.
+#[derive(Debug, Clone, Copy)]
+pub enum Traffic {
+    /// i-j-k order: a row of A stays cached, B is re-read for every output element
+    Streaming,
+    /// A and B are re-read once per `tile` wide block of the other operand
+    Blocked { tile: usize },
+}
+
+impl Traffic {
+    pub fn bytes(self, m: usize, n: usize, k: usize) -> f64 {
+        let (m, n, k) = (m as f64, n as f64, k as f64);
+        let elems = match self {
+            Traffic::Streaming => m * k + m * n * k + m * n,
+            Traffic::Blocked { tile } => {
+                let t = tile as f64;
+                m * k * (n / t).ceil() + k * n * (m / t).ceil() + m * n
+            }
+        };
+        elems * F32_BYTES
+    }
+
+    pub fn intensity(self, m: usize, n: usize, k: usize) -> f64 {
+        flops(m, n, k) / self.bytes(m, n, k)
+    }
+}
+
+/// Every kernel in `verify::KERNELS`, averaged over `num_runs` runs per size.
+pub fn print_roofline_table(sizes: &[usize], num_runs: usize) {
+    println!("MATMUL THROUGHPUT ({} runs)", num_runs);
+    println!(
+        "{:>6} {:>16} {:>12} {:>10} {:>14} {:>10}",
+        "size", "kernel", "secs", "GFLOP/s", "flop/byte", "est GB/s"
+    );
+    for &size in sizes {
+        let gen_ = || (0..size * size).map(|_| fastrand::f32());
+        let a = Matrix::try_from_collection(size, size, gen_()).expect("bad matrix size");
+        let b = Matrix::try_from_collection(size, size, gen_()).expect("bad matrix size");
+
+        for (name, kernel, traffic) in KERNELS {
+            let secs = average_secs(|| kernel(&a, &b), num_runs);
+            println!(
+                "{:>6} {:>16} {:>12.6} {:>10.3} {:>14.3} {:>10.3}",
+                size,
+                name,
+                secs,
+                gflops(size, size, size, secs),
+                traffic.intensity(size, size, size),
+                traffic.bytes(size, size, size) / secs / 1e9
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flops_and_traffic_model() {
+        let n = 4;
+        assert_eq!(flops(n, n, n), 2. * (n as f64).powi(3));
+        assert_eq!(flops(2, 3, 5), 60.);
+        assert_eq!(gflops(1000, 1000, 1000, 1.), 2.);
+
+        // A once, B once per output element, C written once: 16 + 64 + 16 f32
+        assert_eq!(Traffic::Streaming.bytes(n, n, n), 96. * 4.);
+        assert_eq!(Traffic::Streaming.intensity(n, n, n), 128. / 384.);
+
+        // 2x2 tiles: A and B each read twice, C once: 32 + 32 + 16 f32
+        let blocked = Traffic::Blocked { tile: 2 };
+        assert_eq!(blocked.bytes(n, n, n), 80. * 4.);
+        assert_eq!(blocked.intensity(n, n, n), 128. / 320.);
+        // a tile covering everything reads each operand exactly once
+        assert_eq!(Traffic::Blocked { tile: 64 }.bytes(n, n, n), 48. * 4.);
+    }
+}
@@ -0,0 +1,466 @@
+//! Sparse matrices in COO and CSR form, with SpMV and SpGEMM kernels.
+//!
+//! COO is what gets built and written out (and what scipy.sparse reads back as `coo_matrix`),
+//! CSR is what the kernels run on.
+#![allow(dead_code)]
+
+use rayon::prelude::*;
+use std::io::BufRead;
+use std::str::{FromStr, SplitWhitespace};
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Write},
+};
+
+use crate::matrix::Matrix;
+
+/// Triplet form, entries in no particular order and duplicates allowed (they get summed).
+#[derive(Clone, Debug, Default)]
+pub struct CooMatrix {
+    pub rows: usize,
+    pub cols: usize,
+    pub row_idx: Vec<usize>,
+    pub col_idx: Vec<usize>,
+    pub values: Vec<f32>,
+}
+
+/// Compressed sparse row. Row `r` owns `indices[indptr[r]..indptr[r + 1]]`, column indices
+/// are sorted and unique within a row.
+#[derive(Clone, Debug)]
+pub struct CsrMatrix {
+    pub rows: usize,
+    pub cols: usize,
+    pub indptr: Vec<usize>,
+    pub indices: Vec<usize>,
+    pub values: Vec<f32>,
+}
+
+impl CooMatrix {
+    pub fn new(nrows: usize, ncols: usize) -> Self {
+        CooMatrix {
+            rows: nrows,
+            cols: ncols,
+            ..Default::default()
+        }
+    }
+
+    pub fn push(&mut self, row: usize, col: usize, value: f32) {
+        assert!(row < self.rows && col < self.cols, "Entry out of bounds");
+        self.row_idx.push(row);
+        self.col_idx.push(col);
+        self.values.push(value);
+    }
+
+    pub fn nnz(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Random matrix where every entry is stored with probability `density`, values in [0, 1)
+    /// just like `Matrix::output_matrix_for_python`.
+    pub fn random(nrows: usize, ncols: usize, density: f64) -> Self {
+        assert!((0. ..=1.).contains(&density), "Density has to be in [0, 1]");
+
+        let mut coo = CooMatrix::new(nrows, ncols);
+        let expected = (density * (nrows * ncols) as f64) as usize;
+        coo.row_idx.reserve(expected);
+        coo.col_idx.reserve(expected);
+        coo.values.reserve(expected);
+
+        // Bernoulli per entry, row by row, so the output is already in CSR order
+        for r in 0..nrows {
+            for c in 0..ncols {
+                if fastrand::f64() < density {
+                    coo.push(r, c, fastrand::f32());
+                }
+            }
+        }
+        coo
+    }
+
+    pub fn from_dense(m: &Matrix) -> Self {
+        let mut coo = CooMatrix::new(m.rows, m.cols);
+        for r in 0..m.rows {
+            for c in 0..m.cols {
+                let v = m.data[r * m.cols + c];
+                if v != 0. {
+                    coo.push(r, c, v);
+                }
+            }
+        }
+        coo
+    }
+
+    pub fn to_dense(&self) -> Matrix {
+        let mut out = Matrix::zeros(self.rows, self.cols);
+        for ((&r, &c), &v) in self.row_idx.iter().zip(&self.col_idx).zip(&self.values) {
+            out.data[r * self.cols + c] += v;
+        }
+        out
+    }
+
+    pub fn to_csr(&self) -> CsrMatrix {
+        // counting sort by row
+        let mut indptr = vec![0_usize; self.rows + 1];
+        for &r in &self.row_idx {
+            indptr[r + 1] += 1;
+        }
+        for r in 0..self.rows {
+            indptr[r + 1] += indptr[r];
+        }
+
+        let mut next = indptr.clone();
+        let mut indices = vec![0_usize; self.nnz()];
+        let mut values = vec![0_f32; self.nnz()];
+        for ((&r, &c), &v) in self.row_idx.iter().zip(&self.col_idx).zip(&self.values) {
+            indices[next[r]] = c;
+            values[next[r]] = v;
+            next[r] += 1;
+        }
+
+        // sort every row by column and fold duplicates into one entry
+        let mut out = CsrMatrix {
+            rows: self.rows,
+            cols: self.cols,
+            indptr: Vec::with_capacity(self.rows + 1),
+            indices: Vec::with_capacity(self.nnz()),
+            values: Vec::with_capacity(self.nnz()),
+        };
+        out.indptr.push(0);
+        let mut row: Vec<(usize, f32)> = Vec::new();
+        for r in 0..self.rows {
+            row.clear();
+            row.extend((indptr[r]..indptr[r + 1]).map(|i| (indices[i], values[i])));
+            row.sort_unstable_by_key(|&(c, _)| c);
+
+            for &(c, v) in &row {
+                if out.indices.len() > out.indptr[r] && *out.indices.last().unwrap() == c {
+                    *out.values.last_mut().unwrap() += v;
+                } else {
+                    out.indices.push(c);
+                    out.values.push(v);
+                }
+            }
+            out.indptr.push(out.indices.len());
+        }
+        out
+    }
+
+    /// Writes a "rows cols" header and then "row col value" lines, see `read_sparse` in
+    /// py_sparse.py for the scipy.sparse side.
+    pub fn write_triplets(&self, path: &str) -> io::Result<()> {
+        let file = File::create(path)?;
+        let mut writer = BufWriter::new(file);
+
+        writeln!(writer, "{} {}", self.rows, self.cols)?;
+        for ((&r, &c), &v) in self.row_idx.iter().zip(&self.col_idx).zip(&self.values) {
+            writeln!(writer, "{} {} {}", r, c, v)?;
+        }
+        writer.flush()
+    }
+
+    /// Reads back what `write_triplets` wrote. A missing header, a short or unparsable line and
+    /// an entry outside the matrix are all `InvalidData`.
+    pub fn read_triplets(path: &str) -> io::Result<CooMatrix> {
+        fn field<T: FromStr>(parts: &mut SplitWhitespace) -> Option<T> {
+            parts.next()?.parse().ok()
+        }
+        let invalid = |line: usize, what: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}:{}: {}", path, line, what),
+            )
+        };
+
+        let file = File::open(path)?;
+        let mut lines = BufReader::new(file).lines();
+
+        let header = lines.next().ok_or_else(|| invalid(1, "missing header"))??;
+        let mut dims = header.split_whitespace();
+        let (rows, cols) = match (field(&mut dims), field(&mut dims)) {
+            (Some(rows), Some(cols)) => (rows, cols),
+            _ => return Err(invalid(1, "expected \"rows cols\"")),
+        };
+        let mut coo = CooMatrix::new(rows, cols);
+
+        for (i, line) in lines.enumerate() {
+            let line = line?;
+            let mut parts = line.split_whitespace();
+            match (field(&mut parts), field(&mut parts), field(&mut parts)) {
+                (Some(r), Some(c), Some(v)) if r < rows && c < cols => coo.push(r, c, v),
+                _ => {
+                    return Err(invalid(
+                        i + 2,
+                        "expected \"row col value\" inside the matrix",
+                    ));
+                }
+            }
+        }
+        Ok(coo)
+    }
+
+    pub fn output_sparse_for_python() -> std::io::Result<()> {
+        for &ident in &[1, 2] {
+            for &size in &[100, 1000, 10000] {
+                for &density in &[0.001, 0.01, 0.1] {
+                    CooMatrix::random(size, size, density).write_triplets(&format!(
+                        "/home/aperiax/School/SVK/sparse_{}_{}_{}",
+                        ident, size, density
+                    ))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn read_test_sparse(ident: usize, size: usize, density: f64) -> io::Result<CooMatrix> {
+        CooMatrix::read_triplets(&format!(
+            "/home/aperiax/School/SVK/sparse_{}_{}_{}",
+            ident, size, density
+        ))
+    }
+}
+
+/// Dense accumulator row for SpGEMM, `acc` and `seen` are left zeroed after every row.
+struct SpgemmScratch {
+    acc: Vec<f32>,
+    seen: Vec<bool>,
+    cols: Vec<usize>,
+}
+
+impl SpgemmScratch {
+    fn new(width: usize) -> Self {
+        SpgemmScratch {
+            acc: vec![0_f32; width],
+            seen: vec![false; width],
+            cols: Vec::new(),
+        }
+    }
+}
+
+impl From<&CooMatrix> for CsrMatrix {
+    fn from(coo: &CooMatrix) -> Self {
+        coo.to_csr()
+    }
+}
+
+impl CsrMatrix {
+    pub fn nnz(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn from_dense(m: &Matrix) -> Self {
+        CooMatrix::from_dense(m).to_csr()
+    }
+
+    pub fn to_dense(&self) -> Matrix {
+        let mut out = Matrix::zeros(self.rows, self.cols);
+        for r in 0..self.rows {
+            for i in self.indptr[r]..self.indptr[r + 1] {
+                out.data[r * self.cols + self.indices[i]] = self.values[i];
+            }
+        }
+        out
+    }
+
+    pub fn to_coo(&self) -> CooMatrix {
+        let mut coo = CooMatrix::new(self.rows, self.cols);
+        for r in 0..self.rows {
+            for i in self.indptr[r]..self.indptr[r + 1] {
+                coo.push(r, self.indices[i], self.values[i]);
+            }
+        }
+        coo
+    }
+
+    #[inline(always)]
+    fn row_dot(&self, r: usize, x: &[f32]) -> f32 {
+        let (start, end) = (self.indptr[r], self.indptr[r + 1]);
+        self.indices[start..end]
+            .iter()
+            .zip(&self.values[start..end])
+            .map(|(&c, &v)| v * x[c])
+            .sum()
+    }
+
+    /// y = A * x
+    pub fn spmv(&self, x: &[f32]) -> Vec<f32> {
+        assert!(self.cols == x.len(), "Dimension mismatch for spmv");
+        (0..self.rows).map(|r| self.row_dot(r, x)).collect()
+    }
+
+    pub fn spmv_rayon(&self, x: &[f32]) -> Vec<f32> {
+        assert!(self.cols == x.len(), "Dimension mismatch for spmv");
+        (0..self.rows)
+            .into_par_iter()
+            .map(|r| self.row_dot(r, x))
+            .collect()
+    }
+
+    /// Gustavson's row-by-row product for a single output row, appended to `out_idx`/`out_val`.
+    fn spgemm_row(
+        &self,
+        other: &CsrMatrix,
+        r: usize,
+        scratch: &mut SpgemmScratch,
+        out_idx: &mut Vec<usize>,
+        out_val: &mut Vec<f32>,
+    ) {
+        let SpgemmScratch { acc, seen, cols } = scratch;
+
+        cols.clear();
+        for i in self.indptr[r]..self.indptr[r + 1] {
+            let (k, a) = (self.indices[i], self.values[i]);
+            for j in other.indptr[k]..other.indptr[k + 1] {
+                let c = other.indices[j];
+                if !seen[c] {
+                    seen[c] = true;
+                    cols.push(c);
+                }
+                acc[c] += a * other.values[j];
+            }
+        }
+
+        cols.sort_unstable();
+        for &c in cols.iter() {
+            out_idx.push(c);
+            out_val.push(acc[c]);
+            acc[c] = 0.;
+            seen[c] = false;
+        }
+    }
+
+    /// C = A * B, both sparse.
+    pub fn spgemm(&self, other: &CsrMatrix) -> CsrMatrix {
+        assert!(self.cols == other.rows, "Dimension mismatch for spgemm");
+
+        let mut scratch = SpgemmScratch::new(other.cols);
+
+        let mut out = CsrMatrix {
+            rows: self.rows,
+            cols: other.cols,
+            indptr: Vec::with_capacity(self.rows + 1),
+            indices: Vec::new(),
+            values: Vec::new(),
+        };
+        out.indptr.push(0);
+        for r in 0..self.rows {
+            self.spgemm_row(other, r, &mut scratch, &mut out.indices, &mut out.values);
+            out.indptr.push(out.indices.len());
+        }
+        out
+    }
+
+    /// Rows of C are independent, every rayon job gets its own scratch row and the per-row
+    /// results are stitched together afterwards.
+    pub fn spgemm_rayon(&self, other: &CsrMatrix) -> CsrMatrix {
+        assert!(self.cols == other.rows, "Dimension mismatch for spgemm");
+
+        let rows: Vec<(Vec<usize>, Vec<f32>)> = (0..self.rows)
+            .into_par_iter()
+            .map_init(
+                || SpgemmScratch::new(other.cols),
+                |scratch, r| {
+                    let mut idx = Vec::new();
+                    let mut val = Vec::new();
+                    self.spgemm_row(other, r, scratch, &mut idx, &mut val);
+                    (idx, val)
+                },
+            )
+            .collect();
+
+        let nnz = rows.iter().map(|(idx, _)| idx.len()).sum();
+        let mut out = CsrMatrix {
+            rows: self.rows,
+            cols: other.cols,
+            indptr: Vec::with_capacity(self.rows + 1),
+            indices: Vec::with_capacity(nnz),
+            values: Vec::with_capacity(nnz),
+        };
+        out.indptr.push(0);
+        for (idx, val) in rows {
+            out.indices.extend(idx);
+            out.values.extend(val);
+            out.indptr.push(out.indices.len());
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // the benches pull this file in with cfg(test) but without the test harness, so everything
+    // the tests need is imported inside them
+
+    /// Largest absolute difference, relative to the largest reference value.
+    fn rel_diff(got: &[f32], expected: &[f32]) -> f32 {
+        assert_eq!(got.len(), expected.len(), "output sizes differ");
+        let scale = expected
+            .iter()
+            .fold(0_f32, |m, v| m.max(v.abs()))
+            .max(f32::MIN_POSITIVE);
+        let diff = got
+            .iter()
+            .zip(expected)
+            .fold(0_f32, |m, (g, e)| m.max((g - e).abs()));
+        diff / scale
+    }
+
+    #[test]
+    fn coo_csr_dense_round_trip() {
+        use super::*;
+
+        let mut coo = CooMatrix::new(3, 4);
+        coo.push(2, 1, 1.);
+        coo.push(0, 3, 2.);
+        coo.push(2, 1, 0.5);
+        coo.push(0, 0, -1.);
+
+        let csr = coo.to_csr();
+        assert_eq!(csr.indptr, vec![0, 2, 2, 3]);
+        assert_eq!(csr.indices, vec![0, 3, 1]);
+        assert_eq!(csr.values, vec![-1., 2., 1.5]);
+        assert_eq!(csr.to_dense().data, coo.to_dense().data);
+        assert_eq!(CsrMatrix::from_dense(&csr.to_dense()).values, csr.values);
+    }
+
+    #[test]
+    fn triplet_file_round_trip_and_bad_input() {
+        use super::*;
+
+        let path = std::env::temp_dir().join(format!("matmul_sparse_test_{}", std::process::id()));
+        let path = path.to_str().unwrap();
+        let coo = CooMatrix::random(20, 30, 0.2);
+        coo.write_triplets(path).unwrap();
+        let back = CooMatrix::read_triplets(path).unwrap();
+        assert_eq!((back.rows, back.cols), (20, 30));
+        assert_eq!(back.to_dense().data, coo.to_dense().data);
+
+        for bad in ["", "20", "2 2\n0 1", "2 2\n0 x 1.5", "2 2\n2 0 1.5"] {
+            std::fs::write(path, bad).unwrap();
+            let res = CooMatrix::read_triplets(path);
+            assert!(
+                res.is_err_and(|e| e.kind() == io::ErrorKind::InvalidData),
+                "{bad:?}"
+            );
+        }
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn sparse_kernels_match_dense() {
+        use super::*;
+
+        let a = CooMatrix::random(40, 30, 0.1);
+        let b = CooMatrix::random(30, 50, 0.1);
+        let (a_csr, b_csr) = (a.to_csr(), b.to_csr());
+
+        let x = Matrix::try_from_collection(1, 30, (0..30).map(|_| fastrand::f32())).unwrap();
+        let y = a.to_dense().matmul_naive(&x).data;
+        assert!(rel_diff(&a_csr.spmv(&x.data), &y) < 1e-5);
+        assert!(rel_diff(&a_csr.spmv_rayon(&x.data), &y) < 1e-5);
+
+        let c = a.to_dense().matmul_naive(&b.to_dense()).data;
+        assert!(rel_diff(&a_csr.spgemm(&b_csr).to_dense().data, &c) < 1e-5);
+        assert!(rel_diff(&a_csr.spgemm_rayon(&b_csr).to_dense().data, &c) < 1e-5);
+    }
+}
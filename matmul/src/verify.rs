@@ -0,0 +1,317 @@
+//! Numerical accuracy checks for the matmul kernels.
+//!
+//! Every kernel is compared against a product computed in f64 from the same f32 inputs.
+//! Products of two f32 values are exact in f64, so the reference only carries the (much
+//! smaller) f64 summation error and is good enough to judge the f32 kernels against.
+use crate::blas::{Transpose, gemm, gemm_rayon};
+use crate::matrix::{Accumulation, Matrix, RECURSION_CUTOFF, matmul_NdArray, matmul_ndarray_into};
+use crate::roofline::Traffic;
+
+/// Error of one kernel output against the f64 reference.
+#[derive(Debug, Clone, Copy)]
+pub struct ErrorStats {
+    pub max_abs: f64,
+    /// ||C - C_ref||_F / ||C_ref||_F
+    pub rel_frobenius: f64,
+    pub max_ulp: u64,
+    pub mean_ulp: f64,
+}
+
+/// The kind of input the kernels are checked on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatrixKind {
+    /// uniform in [-1, 1)
+    Random,
+    /// mixed signs spread over 2^-12..2^12, lots of cancellation in every dot product
+    IllConditioned,
+    /// small integers, every partial sum is exactly representable in f32
+    IntegerValued,
+}
+
+impl MatrixKind {
+    pub const ALL: [MatrixKind; 3] = [
+        MatrixKind::Random,
+        MatrixKind::IllConditioned,
+        MatrixKind::IntegerValued,
+    ];
+
+    pub fn generate(self, rows: usize, cols: usize, rng: &mut fastrand::Rng) -> Matrix {
+        let data = (0..rows * cols).map(|_| match self {
+            MatrixKind::Random => rng.f32() * 2. - 1.,
+            MatrixKind::IllConditioned => {
+                let sign = if rng.bool() { 1. } else { -1. };
+                sign * (1. + rng.f32()) * 2_f32.powi(rng.i32(-12..=12))
+            }
+            MatrixKind::IntegerValued => rng.i32(-8..=8) as f32,
+        });
+
+        Matrix::try_from_collection(cols, rows, data).expect("generated matrix has wrong size")
+    }
+}
+
+/// A dense kernel, C = A * B.
+pub type Kernel = fn(&Matrix, &Matrix) -> Matrix;
+
+/// All kernels under test, in the order they show up in the reports, with the traffic model the
+/// roofline report places them by. ndarray goes through matrixmultiply, which packs both operands
+/// into cache-sized panels, so it is modelled as blocked.
+pub const KERNELS: [(&str, Kernel, Traffic); 12] = [
+    ("naive", Matrix::matmul_naive, Traffic::Streaming),
+    ("rayon", Matrix::matmul_rayon, Traffic::Streaming),
+    // same traffic as naive, but every B access is a unit-stride read
+    ("transposed", Matrix::matmul_transposed, Traffic::Streaming),
+    (
+        "transposed_rayon",
+        Matrix::matmul_transposed_rayon,
+        Traffic::Streaming,
+    ),
+    (
+        "recursive",
+        Matrix::matmul_recursive,
+        Traffic::Blocked {
+            tile: RECURSION_CUTOFF,
+        },
+    ),
+    (
+        "recursive_rayon",
+        Matrix::matmul_recursive_rayon,
+        Traffic::Blocked {
+            tile: RECURSION_CUTOFF,
+        },
+    ),
+    (
+        "ndarray",
+        |a, b| Matrix::from(matmul_NdArray(a.clone(), b.clone())),
+        Traffic::Blocked { tile: 64 },
+    ),
+    (
+        "ndarray_into",
+        |a, b| {
+            let mut out = Matrix::zeros(a.rows, b.cols);
+            matmul_ndarray_into(a, b, &mut out);
+            out
+        },
+        Traffic::Blocked { tile: 64 },
+    ),
+    // i-k-j, B is streamed once per row of C like in naive
+    (
+        "gemm",
+        |a, b| {
+            let mut out = Matrix::zeros(a.rows, b.cols);
+            gemm(1., a, Transpose::No, b, Transpose::No, 0., &mut out);
+            out
+        },
+        Traffic::Streaming,
+    ),
+    (
+        "gemm_rayon",
+        |a, b| {
+            let mut out = Matrix::zeros(a.rows, b.cols);
+            gemm_rayon(1., a, Transpose::No, b, Transpose::No, 0., &mut out);
+            out
+        },
+        Traffic::Streaming,
+    ),
+    (
+        "naive_kahan",
+        |a, b| a.matmul_naive_with(b, Accumulation::Kahan),
+        Traffic::Streaming,
+    ),
+    (
+        "rayon_kahan",
+        |a, b| a.matmul_rayon_with(b, Accumulation::Kahan),
+        Traffic::Streaming,
+    ),
+];
+
+/// C = A * B accumulated in f64, row-major.
+pub fn reference_f64(a: &Matrix, b: &Matrix) -> Vec<f64> {
+    assert!(a.cols == b.rows, "Dimension mismatch for matmul");
+
+    let mut out = vec![0_f64; a.rows * b.cols];
+    for r in 0..a.rows {
+        for i in 0..a.cols {
+            let lhs = a.data[r * a.cols + i] as f64;
+            let row = &b.data[i * b.cols..(i + 1) * b.cols];
+            for (o, &rhs) in out[r * b.cols..(r + 1) * b.cols].iter_mut().zip(row) {
+                *o += lhs * rhs as f64;
+            }
+        }
+    }
+    out
+}
+
+/// Distance between two floats counted in representable f32 values.
+pub fn ulp_distance(a: f32, b: f32) -> u64 {
+    // map the sign-magnitude bit pattern onto a monotonic integer line
+    fn ordered(x: f32) -> i64 {
+        let bits = x.to_bits();
+        if bits & 0x8000_0000 != 0 {
+            -((bits & 0x7fff_ffff) as i64)
+        } else {
+            bits as i64
+        }
+    }
+    ordered(a).abs_diff(ordered(b))
+}
+
+pub fn compare(computed: &[f32], reference: &[f64]) -> ErrorStats {
+    assert_eq!(computed.len(), reference.len(), "output sizes differ");
+
+    let mut max_abs = 0_f64;
+    let mut diff_sq = 0_f64;
+    let mut ref_sq = 0_f64;
+    let mut max_ulp = 0_u64;
+    let mut ulp_sum = 0_f64;
+
+    for (&c, &r) in computed.iter().zip(reference) {
+        let diff = (c as f64 - r).abs();
+        max_abs = max_abs.max(diff);
+        diff_sq += diff * diff;
+        ref_sq += r * r;
+
+        // against the reference rounded to f32, i.e. the best answer an f32 kernel can give
+        let ulp = ulp_distance(c, r as f32);
+        max_ulp = max_ulp.max(ulp);
+        ulp_sum += ulp as f64;
+    }
+
+    ErrorStats {
+        max_abs,
+        rel_frobenius: if ref_sq > 0. {
+            (diff_sq / ref_sq).sqrt()
+        } else {
+            diff_sq.sqrt()
+        },
+        max_ulp,
+        mean_ulp: ulp_sum / computed.len().max(1) as f64,
+    }
+}
+
+/// Runs every kernel on every matrix kind for a pair of `size` x `size` matrices.
+pub fn verify_all(
+    size: usize,
+    rng: &mut fastrand::Rng,
+) -> Vec<(MatrixKind, &'static str, ErrorStats)> {
+    let mut out = Vec::with_capacity(MatrixKind::ALL.len() * KERNELS.len());
+
+    for kind in MatrixKind::ALL {
+        let a = kind.generate(size, size, rng);
+        let b = kind.generate(size, size, rng);
+        let reference = reference_f64(&a, &b);
+
+        for (name, kernel, _) in KERNELS {
+            out.push((kind, name, compare(&kernel(&a, &b).data, &reference)));
+        }
+    }
+    out
+}
+
+pub fn print_accuracy_report(sizes: &[usize]) {
+    let mut rng = fastrand::Rng::with_seed(0x5eed);
+
+    println!("MATMUL ACCURACY (vs f64 reference)");
+    println!(
+        "{:>6} {:>16} {:>12} {:>12} {:>14} {:>10} {:>10}",
+        "size", "matrix", "kernel", "max_abs", "rel_frobenius", "max_ulp", "mean_ulp"
+    );
+    for &size in sizes {
+        for (kind, name, stats) in verify_all(size, &mut rng) {
+            println!(
+                "{:>6} {:>16} {:>12} {:>12.3e} {:>14.3e} {:>10} {:>10.2}",
+                size,
+                format!("{kind:?}"),
+                name,
+                stats.max_abs,
+                stats.rel_frobenius,
+                stats.max_ulp,
+                stats.mean_ulp
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ulp_distance_crosses_zero() {
+        assert_eq!(ulp_distance(1., 1.), 0);
+        assert_eq!(ulp_distance(1., f32::from_bits(1_f32.to_bits() + 1)), 1);
+        assert_eq!(ulp_distance(-0., 0.), 0);
+        assert_eq!(ulp_distance(-f32::from_bits(1), f32::from_bits(1)), 2);
+    }
+
+    #[test]
+    fn integer_valued_is_exact() {
+        let mut rng = fastrand::Rng::with_seed(1);
+        for (kind, name, stats) in verify_all(48, &mut rng) {
+            if kind == MatrixKind::IntegerValued {
+                assert_eq!(stats.max_abs, 0., "{name} is not exact on integers");
+            }
+        }
+    }
+
+    #[test]
+    fn random_within_tolerance() {
+        let mut rng = fastrand::Rng::with_seed(2);
+        for (kind, name, stats) in verify_all(64, &mut rng) {
+            if kind == MatrixKind::Random {
+                assert!(stats.rel_frobenius < 1e-5, "{name}: {stats:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn ill_conditioned_kernels_agree() {
+        let mut rng = fastrand::Rng::with_seed(3);
+        for (kind, name, stats) in verify_all(64, &mut rng) {
+            if kind == MatrixKind::IllConditioned {
+                assert!(stats.rel_frobenius < 1e-3, "{name}: {stats:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn kahan_beats_plain_on_long_positive_sums() {
+        let mut rng = fastrand::Rng::with_seed(4);
+        let data = |rng: &mut fastrand::Rng| (0..8 * 4096).map(|_| rng.f32()).collect::<Vec<_>>();
+        let a = Matrix::try_from_collection(4096, 8, data(&mut rng)).unwrap();
+        let b = Matrix::try_from_collection(8, 4096, data(&mut rng)).unwrap();
+        let reference = reference_f64(&a, &b);
+
+        let plain = compare(
+            &a.matmul_naive_with(&b, Accumulation::Plain).data,
+            &reference,
+        );
+        let kahan = compare(
+            &a.matmul_naive_with(&b, Accumulation::Kahan).data,
+            &reference,
+        );
+        assert!(
+            kahan.rel_frobenius < plain.rel_frobenius,
+            "{kahan:?} vs {plain:?}"
+        );
+    }
+
+    #[test]
+    fn recursive_kernels_split_odd_shapes() {
+        // well above RECURSION_CUTOFF and odd, so every split axis and uneven halves show up
+        let mut rng = fastrand::Rng::with_seed(6);
+        let a = MatrixKind::Random.generate(150, 97, &mut rng);
+        let b = MatrixKind::Random.generate(97, 203, &mut rng);
+        let reference = reference_f64(&a, &b);
+
+        let kernels: [(&str, Kernel); 4] = [
+            ("transposed", Matrix::matmul_transposed),
+            ("transposed_rayon", Matrix::matmul_transposed_rayon),
+            ("recursive", Matrix::matmul_recursive),
+            ("recursive_rayon", Matrix::matmul_recursive_rayon),
+        ];
+        for (name, kernel) in kernels {
+            let stats = compare(&kernel(&a, &b).data, &reference);
+            assert!(stats.rel_frobenius < 1e-5, "{name}: {stats:?}");
+        }
+    }
+}
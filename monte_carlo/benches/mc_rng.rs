@@ -0,0 +1,48 @@
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+
+#[path = "../src/integrate.rs"]
+mod integrate;
+#[path = "../src/pi.rs"]
+mod pi;
+#[path = "../src/rng.rs"]
+mod rng;
+#[path = "../src/timing.rs"]
+mod timing;
+
+use integrate::Mode;
+use rng::{Pcg64, Philox4x32, RandomSource, SplitMix64, Xoshiro256PlusPlus, simulate_with};
+
+fn criterion_config() -> Criterion {
+    Criterion::default().sample_size(10)
+}
+
+fn bench_rng<R: RandomSource>(
+    group: &mut criterion::BenchmarkGroup<criterion::measurement::WallTime>,
+    size: usize,
+) {
+    for (mode_name, mode) in [("sequential", Mode::Sequential), ("rayon", Mode::Rayon)] {
+        group.bench_with_input(
+            BenchmarkId::new(format!("{} {}", R::NAME, mode_name), size),
+            &size,
+            |b, &size| b.iter(|| simulate_with::<R>(size, 42, mode)),
+        );
+    }
+}
+
+/// Generator x execution mode x size, the same blocked estimator every time.
+pub fn mc_rng(c: &mut Criterion) {
+    let mut group = c.benchmark_group("mc_rng");
+
+    for &size in &[1000000, 2000000, 3000000, 10000000] {
+        group.throughput(Throughput::Elements(size as u64));
+        bench_rng::<fastrand::Rng>(&mut group, size);
+        bench_rng::<SplitMix64>(&mut group, size);
+        bench_rng::<Xoshiro256PlusPlus>(&mut group, size);
+        bench_rng::<Pcg64>(&mut group, size);
+        bench_rng::<Philox4x32>(&mut group, size);
+    }
+    group.finish();
+}
+
+criterion_group! {name = benches; config = criterion_config(); targets = mc_rng}
+criterion_main!(benches);
@@ -0,0 +1,58 @@
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+
+#[path = "../src/integrate.rs"]
+mod integrate;
+#[path = "../src/pi.rs"]
+mod pi;
+#[path = "../src/timing.rs"]
+mod timing;
+#[path = "../src/workloads.rs"]
+mod workloads;
+
+use integrate::Mode;
+use workloads::{Average, CallOption, buffon, price_asian, price_european, random_walk_msd};
+
+fn criterion_config() -> Criterion {
+    Criterion::default().sample_size(10)
+}
+
+const ASIAN_STEPS: usize = 64;
+const WALK_STEPS: usize = 1000;
+
+/// Every workload x execution mode x size, `size` normals / uniforms drawn per run.
+pub fn mc_workloads(c: &mut Criterion) {
+    let mut group = c.benchmark_group("mc_workloads");
+    let option = CallOption {
+        spot: 100.,
+        strike: 100.,
+        rate: 0.05,
+        vol: 0.2,
+        maturity: 1.,
+    };
+
+    for &size in &[1000000, 2000000, 3000000, 10000000] {
+        group.throughput(Throughput::Elements(size as u64));
+        for (mode_name, mode) in [("sequential", Mode::Sequential), ("rayon", Mode::Rayon)] {
+            let id = |name: &str| BenchmarkId::new(format!("{} {}", name, mode_name), size);
+            group.bench_with_input(id("european"), &size, |b, &size| {
+                b.iter(|| price_european(&option, size, 42, mode))
+            });
+            group.bench_with_input(id("asian"), &size, |b, &size| {
+                b.iter(|| {
+                    let paths = size / ASIAN_STEPS;
+                    price_asian(&option, ASIAN_STEPS, Average::Arithmetic, paths, 42, mode)
+                })
+            });
+            group.bench_with_input(id("random walk"), &size, |b, &size| {
+                b.iter(|| random_walk_msd(WALK_STEPS, size / WALK_STEPS, 42, mode))
+            });
+            group.bench_with_input(id("buffon"), &size, |b, &size| {
+                b.iter(|| buffon(0.8, 1., size, 42, mode))
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group! {name = benches; config = criterion_config(); targets = mc_workloads}
+criterion_main!(benches);
@@ -0,0 +1,34 @@
+from random import Random
+from math import sqrt
+import time
+
+BATCH = 1 << 16
+
+
+def simulation_adaptive(target_se: float, max_samples: int, max_secs: float, seed: int = 42):
+    """Batches of BATCH samples until the standard error is below target_se or the budget runs out."""
+    rand = Random(seed)
+    hits: int = 0
+    trials: int = 0
+    se: float = float("inf")
+    start = time.perf_counter()
+
+    while se > target_se and trials < max_samples and time.perf_counter() - start < max_secs:
+        for _ in range(BATCH):
+            x = rand.random()
+            y = rand.random()
+            hits += x*x + y*y < 1
+        trials += BATCH
+
+        p = hits / trials
+        se = 4 * sqrt(p * (1 - p) / trials)
+
+    return (4 * hits / trials, se, trials, se <= target_se, time.perf_counter() - start)
+
+
+if __name__ == "__main__":
+    print("MONTE-CARLO ADAPTIVE (time to accuracy)")
+    print(f"{'target_se':>12} {'samples':>12} {'estimate':>14} {'std_error':>12} {'met':>6} {'secs':>12}")
+    for target in [1e-2, 3e-3, 1e-3, 3e-4]:
+        est, se, trials, met, secs = simulation_adaptive(target, 1 << 34, 30.0)
+        print(f"{target:>12.1e} {trials:>12} {est:>14.10f} {se:>12.3e} {str(met):>6} {secs:>12.3e}")
@@ -0,0 +1,129 @@
+//! Precision-targeted pi: keep sampling in batches until the standard error (or the 95%
+//! half-width) is small enough, or the sample/time budget runs out.
+//!
+//! Batches are whole blocks of the batched kernel, block i always seeded from (seed, i), so
+//! stopping after n samples gives exactly what `simulate_batched_rayon` gives for n samples.
+#![allow(dead_code)]
+
+use rayon::prelude::*;
+use std::time::{Duration, Instant};
+
+use crate::batched::{self, Kernel};
+use crate::integrate::Mode;
+use crate::pi::{BLOCK, PiEstimate, splitmix64};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Target {
+    StdError(f64),
+    /// half-width of the 95% confidence interval, 1.96 standard errors
+    HalfWidth95(f64),
+}
+
+impl Target {
+    fn std_error(&self) -> f64 {
+        match *self {
+            Target::StdError(se) => se,
+            Target::HalfWidth95(hw) => hw / 1.96,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Budget {
+    pub max_samples: u64,
+    pub max_time: Option<Duration>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AdaptiveResult {
+    pub estimate: PiEstimate,
+    /// false when the budget ran out first
+    pub met: bool,
+    pub batches: usize,
+    pub elapsed: Duration,
+}
+
+fn block_hits(kernel: Kernel, seed: u64, block: u64) -> u64 {
+    batched::count_hits(kernel, splitmix64(seed ^ splitmix64(block)), BLOCK)
+}
+
+/// Samples in batches of whole blocks of `BLOCK` points until `target` is met. The first batch
+/// is a single block, there only to get a variance at all; after that every batch is sized to
+/// what the current variance says is still missing, but never more than `batch_blocks`.
+pub fn estimate_pi_adaptive(
+    target: Target,
+    budget: Budget,
+    batch_blocks: usize,
+    seed: u64,
+    mode: Mode,
+) -> AdaptiveResult {
+    let start = Instant::now();
+    let kernel = batched::detect().1;
+    let target_se = target.std_error();
+    let max_blocks = budget.max_samples / BLOCK as u64;
+    let batch_blocks = batch_blocks.max(1) as u64;
+
+    let (mut hits, mut blocks, mut batches) = (0_u64, 0_u64, 0);
+    let mut estimate = PiEstimate::new(0, 0, None);
+    loop {
+        let met = blocks > 0 && estimate.std_error <= target_se;
+        let out_of_time = budget.max_time.is_some_and(|t| start.elapsed() >= t);
+        if met || blocks >= max_blocks || out_of_time {
+            return AdaptiveResult {
+                estimate,
+                met,
+                batches,
+                elapsed: start.elapsed(),
+            };
+        }
+
+        // se shrinks as 1/sqrt(n): n * (se / target)^2 samples in total should do it
+        let wanted = if blocks == 0 {
+            1
+        } else {
+            let ratio = estimate.std_error / target_se;
+            ((blocks as f64 * ratio * ratio).ceil() as u64).saturating_sub(blocks)
+        };
+        let next = wanted.clamp(1, batch_blocks).min(max_blocks - blocks);
+
+        let range = blocks..blocks + next;
+        hits += match mode {
+            Mode::Sequential => range.map(|b| block_hits(kernel, seed, b)).sum::<u64>(),
+            Mode::Rayon => range
+                .into_par_iter()
+                .map(|b| block_hits(kernel, seed, b))
+                .sum::<u64>(),
+        };
+        blocks += next;
+        batches += 1;
+        estimate = PiEstimate::new(hits, blocks * BLOCK as u64, None);
+    }
+}
+
+pub fn print_adaptive_report(seed: u64) {
+    println!("MONTE-CARLO ADAPTIVE (time to accuracy)");
+    println!(
+        "{:>12} {:>10} {:>12} {:>14} {:>12} {:>8} {:>8} {:>12}",
+        "target_se", "mode", "samples", "estimate", "std_error", "batches", "met", "secs"
+    );
+    let budget = Budget {
+        max_samples: 1 << 34,
+        max_time: Some(Duration::from_secs(30)),
+    };
+    for target in [1e-2, 3e-3, 1e-3, 3e-4, 1e-4] {
+        for (name, mode) in [("sequential", Mode::Sequential), ("rayon", Mode::Rayon)] {
+            let res = estimate_pi_adaptive(Target::StdError(target), budget, 256, seed, mode);
+            println!(
+                "{:>12.1e} {:>10} {:>12} {:>14.10} {:>12.3e} {:>8} {:>8} {:>12.3e}",
+                target,
+                name,
+                res.estimate.trials,
+                res.estimate.estimate,
+                res.estimate.std_error,
+                res.batches,
+                res.met,
+                res.elapsed.as_secs_f64()
+            );
+        }
+    }
+}
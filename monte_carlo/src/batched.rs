@@ -0,0 +1,198 @@
+//! Batched pi kernel: four xoshiro256++ streams side by side, one per SIMD lane.
+//!
+//! Every iteration draws an x and a y for each of the four lanes, tests four points at once and
+//! adds the comparison masks straight into integer hit counters, no per-sample division. The
+//! AVX2 kernel and the scalar fallback do exactly the same arithmetic on the same state layout,
+//! so they return the same count for the same seed and the dispatch is invisible in the results.
+#![allow(dead_code)]
+
+use rayon::prelude::*;
+
+use crate::pi::{BLOCK, PiEstimate, splitmix64};
+use crate::rng::{RandomSource, SplitMix64};
+
+pub const LANES: usize = 4;
+
+/// xoshiro256++ state for every lane, word-major (`s[word][lane]`) so one word of all four lanes
+/// is one 256-bit load.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LaneState {
+    pub s: [[u64; LANES]; 4],
+}
+
+impl LaneState {
+    pub fn from_seed(seed: u64) -> Self {
+        let mut s = [[0; LANES]; 4];
+        for lane in 0..LANES {
+            let mut sm = SplitMix64::from_seed(splitmix64(seed ^ lane as u64));
+            for word in s.iter_mut() {
+                word[lane] = sm.next_u64();
+            }
+        }
+        LaneState { s }
+    }
+
+    /// One xoshiro256++ step of every lane.
+    #[inline(always)]
+    fn next(&mut self) -> [u64; LANES] {
+        let [s0, s1, s2, s3] = &mut self.s;
+        std::array::from_fn(|l| {
+            let out = s0[l]
+                .wrapping_add(s3[l])
+                .rotate_left(23)
+                .wrapping_add(s0[l]);
+            let t = s1[l] << 17;
+            s2[l] ^= s0[l];
+            s3[l] ^= s1[l];
+            s1[l] ^= s2[l];
+            s0[l] ^= s3[l];
+            s2[l] ^= t;
+            s3[l] = s3[l].rotate_left(45);
+            out
+        })
+    }
+
+    /// Which of the next four points (one per lane) land inside the quarter circle.
+    #[inline(always)]
+    fn round(&mut self) -> [bool; LANES] {
+        let xs = self.next();
+        let ys = self.next();
+        std::array::from_fn(|l| {
+            let (x, y) = (to_unit(xs[l]), to_unit(ys[l]));
+            x * x + y * y < 1.
+        })
+    }
+}
+
+/// Top 52 bits as the mantissa of a double in [1, 2), minus 1. No int to float conversion,
+/// which AVX2 doesn't have for 64-bit lanes.
+#[inline(always)]
+fn to_unit(bits: u64) -> f64 {
+    f64::from_bits((bits >> 12) | 0x3FF0_0000_0000_0000) - 1.
+}
+
+/// Runs `iters` rounds, 4 points each, and returns the hits.
+pub type Kernel = fn(&mut LaneState, usize) -> u64;
+
+pub fn scalar(state: &mut LaneState, iters: usize) -> u64 {
+    let mut hits = [0_u64; LANES];
+    for _ in 0..iters {
+        for (h, inside) in hits.iter_mut().zip(state.round()) {
+            *h += inside as u64;
+        }
+    }
+    hits.iter().sum()
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use super::LaneState;
+    use std::arch::x86_64::*;
+
+    #[target_feature(enable = "avx2")]
+    fn rotl<const L: i32, const R: i32>(x: __m256i) -> __m256i {
+        _mm256_or_si256(_mm256_slli_epi64::<L>(x), _mm256_srli_epi64::<R>(x))
+    }
+
+    #[target_feature(enable = "avx2")]
+    fn next(s: &mut [__m256i; 4]) -> __m256i {
+        let out = _mm256_add_epi64(rotl::<23, 41>(_mm256_add_epi64(s[0], s[3])), s[0]);
+        let t = _mm256_slli_epi64::<17>(s[1]);
+        s[2] = _mm256_xor_si256(s[2], s[0]);
+        s[3] = _mm256_xor_si256(s[3], s[1]);
+        s[1] = _mm256_xor_si256(s[1], s[2]);
+        s[0] = _mm256_xor_si256(s[0], s[3]);
+        s[2] = _mm256_xor_si256(s[2], t);
+        s[3] = rotl::<45, 19>(s[3]);
+        out
+    }
+
+    #[target_feature(enable = "avx2")]
+    fn to_unit(bits: __m256i) -> __m256d {
+        let exponent = _mm256_set1_epi64x(0x3FF0_0000_0000_0000);
+        let one = _mm256_or_si256(_mm256_srli_epi64::<12>(bits), exponent);
+        _mm256_sub_pd(_mm256_castsi256_pd(one), _mm256_set1_pd(1.))
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub fn avx2(state: &mut LaneState, iters: usize) -> u64 {
+        // SAFETY: every word of the state is 4 u64s, loadu/storeu have no alignment requirement
+        let mut s: [__m256i; 4] =
+            std::array::from_fn(|w| unsafe { _mm256_loadu_si256(state.s[w].as_ptr().cast()) });
+        let one = _mm256_set1_pd(1.);
+        let mut hits = _mm256_setzero_si256();
+
+        for _ in 0..iters {
+            let x = to_unit(next(&mut s));
+            let y = to_unit(next(&mut s));
+            // mul + add rather than fma, so the rounding matches the scalar kernel
+            let r2 = _mm256_add_pd(_mm256_mul_pd(x, x), _mm256_mul_pd(y, y));
+            // an all-ones mask is -1, subtracting it counts the hit
+            let inside = _mm256_castpd_si256(_mm256_cmp_pd::<_CMP_LT_OQ>(r2, one));
+            hits = _mm256_sub_epi64(hits, inside);
+        }
+
+        for (w, v) in s.iter().enumerate() {
+            // SAFETY: see above
+            unsafe { _mm256_storeu_si256(state.s[w].as_mut_ptr().cast(), *v) };
+        }
+        let mut out = [0_u64; 4];
+        // SAFETY: out holds 4 u64s
+        unsafe { _mm256_storeu_si256(out.as_mut_ptr().cast(), hits) };
+        out.iter().sum()
+    }
+
+    // Only handed out by `available` after checking for avx2.
+    pub fn avx2_checked(state: &mut LaneState, iters: usize) -> u64 {
+        // SAFETY: see above
+        unsafe { avx2(state, iters) }
+    }
+}
+
+/// Every kernel this CPU can run, best first. The scalar one is always last.
+pub fn available() -> Vec<(&'static str, Kernel)> {
+    let mut out: Vec<(&'static str, Kernel)> = Vec::new();
+
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx2") {
+            out.push(("avx2", x86::avx2_checked));
+        }
+    }
+
+    out.push(("scalar", scalar));
+    out
+}
+
+pub fn detect() -> (&'static str, Kernel) {
+    available()[0]
+}
+
+/// Hits among `n` points from a fresh state seeded with `seed`. The last n % 4 points come from
+/// one more scalar round, only its first lanes count.
+pub fn count_hits(kernel: Kernel, seed: u64, n: usize) -> u64 {
+    let mut state = LaneState::from_seed(seed);
+    let hits = kernel(&mut state, n / LANES);
+    let rest = state.round()[..n % LANES]
+        .iter()
+        .filter(|&&inside| inside)
+        .count();
+    hits + rest as u64
+}
+
+/// Single core, one state for the whole run.
+pub fn simulate_batched(kernel: Kernel, max_iter: usize, seed: u64) -> PiEstimate {
+    PiEstimate::new(count_hits(kernel, seed, max_iter), max_iter as u64, None)
+}
+
+/// One state per block and rayon across blocks, reproducible on any thread count.
+pub fn simulate_batched_rayon(kernel: Kernel, max_iter: usize, seed: u64) -> PiEstimate {
+    let hits: u64 = (0..max_iter.div_ceil(BLOCK))
+        .into_par_iter()
+        .map(|b| {
+            let seed = splitmix64(seed ^ splitmix64(b as u64));
+            count_hits(kernel, seed, BLOCK.min(max_iter - b * BLOCK))
+        })
+        .sum();
+    PiEstimate::new(hits, max_iter as u64, None)
+}
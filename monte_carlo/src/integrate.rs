@@ -0,0 +1,214 @@
+//! Monte Carlo integration of arbitrary closures over d-dimensional boxes.
+//!
+//! Same block/stream layout as the pi estimator: block i always draws from `block_rng(seed, i)`,
+//! so sequential and rayon runs with one seed give the same estimate bit for bit.
+#![allow(dead_code)]
+
+use rayon::prelude::*;
+
+use fastrand::Rng;
+
+use crate::pi::{BLOCK, block_rng};
+
+/// Seed used by `integrate` and `integrate_rayon`.
+pub const DEFAULT_SEED: u64 = 0x5EED;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Sequential,
+    Rayon,
+}
+
+/// Running mean and sum of squared deviations (Welford), mergeable with Chan's formula.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Moments {
+    pub n: u64,
+    pub mean: f64,
+    pub m2: f64,
+}
+
+impl Moments {
+    #[inline(always)]
+    pub fn push(&mut self, x: f64) {
+        self.n += 1;
+        let delta = x - self.mean;
+        self.mean += delta / self.n as f64;
+        self.m2 += delta * (x - self.mean);
+    }
+
+    pub fn merge(self, other: Moments) -> Moments {
+        if self.n == 0 {
+            return other;
+        }
+        if other.n == 0 {
+            return self;
+        }
+        let (na, nb) = (self.n as f64, other.n as f64);
+        let n = na + nb;
+        let delta = other.mean - self.mean;
+        Moments {
+            n: self.n + other.n,
+            mean: self.mean + delta * nb / n,
+            m2: self.m2 + other.m2 + delta * delta * na * nb / n,
+        }
+    }
+
+    /// Unbiased sample variance, 0 for fewer than two samples.
+    pub fn variance(&self) -> f64 {
+        if self.n < 2 {
+            return 0.;
+        }
+        self.m2 / (self.n - 1) as f64
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Integral {
+    pub samples: u64,
+    pub estimate: f64,
+    pub std_error: f64,
+    /// normal approximation, estimate +- 1.96 standard errors
+    pub ci95: (f64, f64),
+}
+
+impl Integral {
+    /// Integral of a box of volume `volume` from the moments of f at uniform points in it.
+    pub fn from_moments(m: Moments, volume: f64) -> Self {
+        let estimate = volume * m.mean;
+        let std_error = volume * (m.variance() / m.n.max(1) as f64).sqrt();
+        Integral {
+            samples: m.n,
+            estimate,
+            std_error,
+            ci95: (estimate - 1.96 * std_error, estimate + 1.96 * std_error),
+        }
+    }
+}
+
+pub fn volume(bounds: &[(f64, f64)]) -> f64 {
+    bounds.iter().map(|(lo, hi)| hi - lo).product()
+}
+
+/// Runs `block(rng, len)` on every block of an `n` sample run, each block with its own stream,
+/// and returns the results in block order whatever the mode.
+pub fn map_blocks<T, B>(n: usize, seed: u64, mode: Mode, block: B) -> Vec<T>
+where
+    T: Send,
+    B: Fn(&mut Rng, usize) -> T + Sync,
+{
+    let run = |b: usize| block(&mut block_rng(seed, b as u64), BLOCK.min(n - b * BLOCK));
+    match mode {
+        Mode::Sequential => (0..n.div_ceil(BLOCK)).map(run).collect(),
+        Mode::Rayon => (0..n.div_ceil(BLOCK)).into_par_iter().map(run).collect(),
+    }
+}
+
+/// Fills `x` with a uniform point of the box.
+#[inline(always)]
+pub fn uniform_point(rng: &mut Rng, bounds: &[(f64, f64)], x: &mut [f64]) {
+    for (xi, (lo, hi)) in x.iter_mut().zip(bounds) {
+        *xi = lo + (hi - lo) * rng.f64();
+    }
+}
+
+/// `n` uniform samples of `f` over the box `bounds`, one (lo, hi) pair per dimension.
+pub fn integrate_with<F>(f: F, bounds: &[(f64, f64)], n: usize, seed: u64, mode: Mode) -> Integral
+where
+    F: Fn(&[f64]) -> f64 + Sync,
+{
+    let per_block = map_blocks(n, seed, mode, |rng, len| {
+        // one point buffer per block, reused for every sample
+        let mut x = vec![0.; bounds.len()];
+        let mut m = Moments::default();
+        for _ in 0..len {
+            uniform_point(rng, bounds, &mut x);
+            m.push(f(&x));
+        }
+        m
+    });
+    // merged in block order either way, a rayon reduce would round differently per thread count
+    let m = per_block
+        .into_iter()
+        .fold(Moments::default(), Moments::merge);
+    Integral::from_moments(m, volume(bounds))
+}
+
+pub fn integrate<F>(f: F, bounds: &[(f64, f64)], n: usize) -> Integral
+where
+    F: Fn(&[f64]) -> f64 + Sync,
+{
+    integrate_with(f, bounds, n, DEFAULT_SEED, Mode::Sequential)
+}
+
+pub fn integrate_rayon<F>(f: F, bounds: &[(f64, f64)], n: usize) -> Integral
+where
+    F: Fn(&[f64]) -> f64 + Sync,
+{
+    integrate_with(f, bounds, n, DEFAULT_SEED, Mode::Rayon)
+}
+
+/// ∫_0^1 exp(-x²) dx, erf(1) * sqrt(pi) / 2.
+pub const GAUSS_01: f64 = 0.746_824_132_812_427;
+
+/// Integrands with closed form values on [0, 1]^d, used by the tests and the report.
+pub fn sum_of_squares(x: &[f64]) -> f64 {
+    x.iter().map(|v| v * v).sum()
+}
+
+pub fn gaussian(x: &[f64]) -> f64 {
+    (-sum_of_squares(x)).exp()
+}
+
+/// Genz's oscillatory family with u = 0, cos(Σ a_i x_i).
+pub fn oscillatory(a: &[f64]) -> impl Fn(&[f64]) -> f64 + Sync + '_ {
+    move |x| x.iter().zip(a).map(|(xi, ai)| xi * ai).sum::<f64>().cos()
+}
+
+/// Re Π (e^{i a_k} - 1) / (i a_k), the exact value of `oscillatory(a)` on [0, 1]^d.
+pub fn oscillatory_exact(a: &[f64]) -> f64 {
+    let (re, _) = a.iter().fold((1., 0.), |(re, im), &ak| {
+        let (fr, fi) = (ak.sin() / ak, (1. - ak.cos()) / ak);
+        (re * fr - im * fi, re * fi + im * fr)
+    });
+    re
+}
+
+pub type Integrand<'a> = &'a (dyn Fn(&[f64]) -> f64 + Sync);
+
+pub fn print_integration_report(n: usize, num_runs: usize) {
+    println!("MONTE-CARLO INTEGRATION");
+    println!(
+        "{:>12} {:>4} {:>14} {:>14} {:>12} {:>12} {:>12} {:>12}",
+        "integrand", "dim", "exact", "estimate", "std_error", "abs_error", "seq_secs", "rayon_secs"
+    );
+    for d in [1, 3, 5, 10] {
+        let unit = vec![(0., 1.); d];
+        let a: Vec<f64> = (1..=d).map(|k| k as f64 / d as f64).collect();
+        let cases: [(&str, Integrand, f64); 3] = [
+            ("polynomial", &sum_of_squares, d as f64 / 3.),
+            ("gaussian", &gaussian, GAUSS_01.powi(d as i32)),
+            ("oscillatory", &oscillatory(&a), oscillatory_exact(&a)),
+        ];
+
+        for (name, f, exact) in cases {
+            let time = |mode| {
+                crate::timing::average_secs(
+                    || integrate_with(f, &unit, n, DEFAULT_SEED, mode),
+                    num_runs,
+                )
+            };
+            let res = integrate_rayon(f, &unit, n);
+            println!(
+                "{:>12} {:>4} {:>14.8} {:>14.8} {:>12.3e} {:>12.3e} {:>12.3e} {:>12.3e}",
+                name,
+                d,
+                exact,
+                res.estimate,
+                res.std_error,
+                (res.estimate - exact).abs(),
+                time(Mode::Sequential),
+                time(Mode::Rayon)
+            );
+        }
+    }
+}
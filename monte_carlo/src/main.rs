@@ -1,59 +1,454 @@
 //! monte carlo simulation in rust, preferably sequentiall and then parallel
-use std::{
-    f32::{self, consts::PI},
-    time::{Duration, Instant},
-};
+mod adaptive;
+mod batched;
+mod integrate;
+mod pi;
+mod qmc;
+mod rng;
+mod timing;
+mod variance;
+mod workloads;
 
-use fastrand::f32;
+use timing::average_secs;
 
-/// Monte carlo approximation of pi on single thread
-// #[inline(always)]
-fn simulate(max_iter: usize) -> (f32, i32) {
-    let mut in_: f32 = 0.;
-    let mut out_: f32 = 0.;
+fn main() {
+    let num_runs: usize = 10;
+    let mut res_mc: Vec<f64> = Vec::with_capacity(num_runs);
+    let mut res_mc_rayon: Vec<f64> = Vec::with_capacity(num_runs);
+    let (kernel_name, kernel) = batched::detect();
+    let mut res_mc_batched: Vec<f64> = Vec::with_capacity(num_runs);
+    let mut res_mc_batched_rayon: Vec<f64> = Vec::with_capacity(num_runs);
+    // let mut results: Vec<f32> = Vec::new();
 
-    let mut iter = 0;
-    let mut res = 0_f32;
+    for &size in &[1000000, 2000000, 3000000, 10000000] {
+        // same blocks and streams as the rayon run, only the thread count differs
+        res_mc.push(average_secs(
+            || pi::simulate_seeded(size, 42, None),
+            num_runs,
+        ));
+        res_mc_rayon.push(average_secs(
+            || pi::simulate_rayon(size, 42, None),
+            num_runs,
+        ));
+        res_mc_batched.push(average_secs(
+            || batched::simulate_batched(kernel, size, 42),
+            num_runs,
+        ));
+        res_mc_batched_rayon.push(average_secs(
+            || batched::simulate_batched_rayon(kernel, size, 42),
+            num_runs,
+        ));
+    }
 
-    for _ in 0..max_iter {
-        let x: f32 = f32();
-        let y: f32 = f32();
+    println!(
+        "MONTE-CARLO\nAverage results for 1e6, 2e6, 3e6 and 1e7 iters: {:?}",
+        res_mc
+    );
+    println!(
+        "MONTE-CARLO RAYON\nAverage results for 1e6, 2e6, 3e6 and 1e7 iters: {:?}",
+        res_mc_rayon
+    );
+    println!(
+        "MONTE-CARLO BATCHED ({})\nAverage results for 1e6, 2e6, 3e6 and 1e7 iters: {:?}",
+        kernel_name, res_mc_batched
+    );
+    println!(
+        "MONTE-CARLO BATCHED RAYON ({})\nAverage results for 1e6, 2e6, 3e6 and 1e7 iters: {:?}",
+        kernel_name, res_mc_batched_rayon
+    );
 
-        // predication pattern
-        let inside = (x * x + y * y < 1.) as u32;
+    pi::print_accuracy_report(&[1000000, 2000000, 3000000, 10000000], 42);
+    integrate::print_integration_report(1000000, num_runs);
+    variance::print_variance_report(1000000, num_runs);
+    qmc::print_qmc_report(20, 42);
+    rng::print_rng_report(10000000, 32, num_runs);
+    adaptive::print_adaptive_report(42);
+    workloads::print_workloads_report(1000000, num_runs);
+}
 
-        in_ += inside as f32;
-        out_ += 1. - inside as f32;
-        res = 4. * (in_ / (in_ + out_));
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        // println!("{res}, diff: {diff}, in: {in_}, out: {out_}");
-        iter += 1;
+    #[test]
+    fn rayon_is_reproducible_on_any_thread_count() {
+        // not a multiple of the block size, so the short last block is covered too
+        let n = 3 * pi::BLOCK + 1234;
+        let expected = pi::simulate_seeded(n, 7, Some(3));
+
+        for threads in [1, 2, 3, 8] {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap();
+            assert_eq!(pool.install(|| pi::simulate_rayon(n, 7, Some(3))), expected);
+        }
+        assert_ne!(pi::simulate_rayon(n, 8, None).hits, expected.hits);
+        assert!(expected.abs_error() < 0.05);
     }
-    (res, iter)
-}
 
-fn measure_raw<T>(mut f: impl FnMut() -> T) -> Duration {
-    let start = Instant::now();
-    let _ = f();
-    Instant::now() - start
-}
+    #[test]
+    fn simulate_counts_past_f32_precision() {
+        // well past 2^24 hits, where an f32 tally stops counting
+        let n = 1 << 25;
+        let est = pi::simulate(n);
+        assert_eq!(est.trials, n as u64);
+        assert!(est.abs_error() < 5. * est.std_error, "{est:?}");
+    }
 
-fn main() {
-    let num_runs: usize = 10;
-    let mut res_mc: Vec<f64> = Vec::with_capacity(num_runs);
-    // let mut results: Vec<f32> = Vec::new();
+    #[test]
+    fn estimate_statistics_are_consistent() {
+        let n = 2 * pi::BLOCK + 17;
+        let traced = pi::simulate_seeded(n, 3, Some(4));
+        let plain = pi::simulate_seeded(n, 3, None);
 
-    for &size in &[1000000, 2000000, 3000000, 10000000] {
-        let mut temp_: Vec<Duration> = Vec::with_capacity(num_runs);
-        for _ in 0..num_runs {
-            temp_.push(measure_raw(|| simulate(size)));
-            // results.push(simulate(size).0);
+        // tracing only reads the counters, it never changes the result
+        assert_eq!(traced.hits, plain.hits);
+        assert_eq!(plain.trace, None);
+        assert_eq!(traced.trials, n as u64);
+        assert_eq!(traced.estimate, 4. * traced.hits as f64 / n as f64);
+        assert!(traced.ci95.0 < traced.estimate && traced.estimate < traced.ci95.1);
+        let half_width = traced.ci95.1 - traced.estimate;
+        assert!((half_width - 1.96 * traced.std_error).abs() < 1e-12);
+
+        let trace = traced.trace.unwrap();
+        let trials: Vec<u64> = trace.iter().map(|c| c.trials).collect();
+        let expected: Vec<u64> = pi::log_checkpoints(n, 4)
+            .iter()
+            .map(|&c| c as u64)
+            .collect();
+        assert_eq!(trials, expected);
+        let last = trace.last().unwrap();
+        assert_eq!(
+            (last.estimate, last.std_error),
+            (traced.estimate, traced.std_error)
+        );
+        assert!(trace.windows(2).all(|w| w[0].trials < w[1].trials));
+    }
+
+    #[test]
+    fn integrate_known_values() {
+        use integrate::*;
+
+        let check = |f: &(dyn Fn(&[f64]) -> f64 + Sync), bounds: &[(f64, f64)], exact: f64| {
+            let seq = integrate(f, bounds, 200_000);
+            assert_eq!(integrate_rayon(f, bounds, 200_000), seq);
+            assert_eq!(seq.samples, 200_000);
+            // fixed seed, so this can't flake, 5 standard errors is just a generous margin
+            let err = (seq.estimate - exact).abs();
+            assert!(
+                err <= 5. * seq.std_error + 1e-12,
+                "{seq:?} vs {exact}, dim {}",
+                bounds.len()
+            );
+        };
+
+        for d in 1..=10 {
+            let unit = vec![(0., 1.); d];
+            let a: Vec<f64> = (1..=d).map(|k| 0.5 + k as f64).collect();
+            check(&sum_of_squares, &unit, d as f64 / 3.);
+            check(&gaussian, &unit, GAUSS_01.powi(d as i32));
+            check(&oscillatory(&a), &unit, oscillatory_exact(&a));
         }
-        res_mc.push(temp_.iter().map(|&time| time.as_secs_f64()).sum::<f64>() / temp_.len() as f64)
+
+        // non-unit box: ∫_1^3 x dx * ∫_-1^2 y dy = 4 * 1.5
+        check(&|x: &[f64]| x[0] * x[1], &[(1., 3.), (-1., 2.)], 6.);
+        // constant integrand, exact with zero error
+        let c = integrate(|_: &[f64]| 2., &[(0., 2.), (0., 0.5)], 1000);
+        assert_eq!((c.estimate, c.std_error), (2., 0.));
     }
 
-    println!(
-        "MONTE-CARLO\nAverage results for 1e6, 2e6, 3e6 and 1e7 iters: {:?}",
-        res_mc
-    )
+    #[test]
+    fn variance_reduction_beats_plain() {
+        use integrate::{Mode, integrate_with};
+        use variance::*;
+
+        let unit = [(0., 1.)];
+        let exp = |x: &[f64]| x[0].exp();
+        let exact = std::f64::consts::E - 1.;
+        let n = 100_000;
+
+        let plain = integrate_with(exp, &unit, n, 1, Mode::Sequential);
+        let reduced = [
+            antithetic(exp, &unit, n, 1, Mode::Sequential),
+            stratified(exp, &unit, 50, n, 1, Mode::Sequential),
+            importance(exp, linear_sample, linear_pdf, 1, n, 1, Mode::Sequential),
+            control_variate(
+                exp,
+                |x: &[f64]| 1. + x[0],
+                1.5,
+                &unit,
+                n,
+                1,
+                Mode::Sequential,
+            ),
+        ];
+        for res in reduced {
+            assert_eq!(res.samples, n as u64, "{res:?}");
+            assert!(
+                (res.estimate - exact).abs() <= 5. * res.std_error,
+                "{res:?}"
+            );
+            // every one of them cuts the variance at least 5x on e^x
+            assert!(res.std_error * res.std_error * 5. < plain.std_error * plain.std_error);
+        }
+
+        // stratified on a 2d grid, rayon and sequential agree exactly
+        let square = [(0., 2.), (0., 1.)];
+        let f = |x: &[f64]| x[0] * x[1];
+        let seq = stratified(f, &square, 8, 10_000, 3, Mode::Sequential);
+        assert_eq!(stratified(f, &square, 8, 10_000, 3, Mode::Rayon), seq);
+        assert_eq!(seq.samples, 64 * (10_000 / 64));
+        assert!((seq.estimate - 1.).abs() <= 5. * seq.std_error);
+
+        // in 5 dimensions the exponential density still beats uniform points
+        let cube = [(0., 1.); 5];
+        let gauss = integrate::gaussian;
+        let exact = integrate::GAUSS_01.powi(5);
+        let g = importance(
+            gauss,
+            exponential_sample,
+            exponential_pdf,
+            5,
+            n,
+            5,
+            Mode::Rayon,
+        );
+        let plain = integrate_with(gauss, &cube, n, 5, Mode::Rayon);
+        assert!((g.estimate - exact).abs() <= 5. * g.std_error, "{g:?}");
+        assert!(g.std_error < plain.std_error);
+    }
+
+    #[test]
+    #[should_panic(expected = "strata^d cells overflow")]
+    fn stratified_rejects_overflowing_cell_count() {
+        let cube = [(0., 1.); 20];
+        variance::stratified(|_| 1., &cube, 16, 1000, 1, integrate::Mode::Sequential);
+    }
+
+    #[test]
+    fn qmc_sequences() {
+        use integrate::{GAUSS_01, Mode, gaussian, integrate_with};
+        use qmc::*;
+
+        let sobol = Sobol::new(16).unwrap();
+        let mut x = [0.; 16];
+        let first: Vec<(f64, f64)> = (0..4)
+            .map(|i| {
+                sobol.point(i, &mut x);
+                (x[0], x[1])
+            })
+            .collect();
+        assert_eq!(first, [(0., 0.), (0.5, 0.5), (0.75, 0.25), (0.25, 0.75)]);
+
+        // every 1d projection of the first 2^k points hits each interval of width 2^-k once
+        let n = 1 << 10;
+        for d in 0..16 {
+            let mut hit = vec![false; n];
+            for i in 0..n as u64 {
+                sobol.point(i, &mut x);
+                let cell = (x[d] * n as f64) as usize;
+                assert!(!hit[cell], "dimension {d}, point {i}");
+                hit[cell] = true;
+            }
+        }
+        assert!(Sobol::new(17).is_err());
+        assert!(Halton::new(17).is_err());
+        assert!(Halton::with_bases(vec![2, 1]).is_err());
+
+        let halton = Halton::new(2).unwrap();
+        let mut y = [0.; 2];
+        halton.point(4, &mut y);
+        // index 5: 5 = 101 in base 2, 12 in base 3
+        assert_eq!(y[0], 0.625);
+        assert!((y[1] - 7. / 9.).abs() < 1e-15);
+
+        // a smooth integrand converges much faster than with pseudo-random points
+        let unit = [(0., 1.); 3];
+        let exact = GAUSS_01.powi(3);
+        let n = 1 << 14;
+        let mc = (integrate_with(gaussian, &unit, n, 1, Mode::Sequential).estimate - exact).abs();
+        for res in [
+            integrate_qmc(
+                gaussian,
+                &unit,
+                n,
+                &Halton::new(3).unwrap(),
+                Mode::Sequential,
+            ),
+            integrate_qmc(gaussian, &unit, n, &Sobol::new(3).unwrap(), Mode::Rayon),
+        ] {
+            assert!(
+                (res.estimate - exact).abs() * 10. < mc,
+                "{res:?} vs mc error {mc}"
+            );
+        }
+
+        let sobol = Sobol::new(3).unwrap();
+        let rqmc = |mode| {
+            integrate_rqmc(gaussian, &unit, n / 8, 8, 9, mode, |rng| {
+                sobol.digitally_shifted(rng)
+            })
+        };
+        let res = rqmc(Mode::Sequential);
+        assert_eq!(rqmc(Mode::Rayon), res);
+        assert!(
+            (res.estimate - exact).abs() <= 5. * res.std_error,
+            "{res:?}"
+        );
+        let shifted = integrate_rqmc(gaussian, &unit, n / 8, 8, 9, Mode::Rayon, |rng| {
+            RandomShift::new(Halton::new(3).unwrap(), rng)
+        });
+        assert!(
+            (shifted.estimate - exact).abs() <= 5. * shifted.std_error,
+            "{shifted:?}"
+        );
+    }
+
+    #[test]
+    fn rng_known_answers_and_reproducibility() {
+        use integrate::Mode;
+        use rng::*;
+
+        // reference outputs: SplitMix64 from 0, Random123's philox4x32-10 zero key/counter
+        let mut sm = SplitMix64::from_seed(0);
+        assert_eq!(sm.next_u64(), 0xE220_A839_7B1D_CDAF);
+        assert_eq!(sm.next_u64(), 0x6E78_9E6A_A1B9_65F4);
+        assert_eq!(
+            Philox4x32::block([0, 0], [0, 0, 0, 0]),
+            [0x6627_E8D5, 0xE169_C58D, 0xBC57_AC4C, 0x9B00_DBD8]
+        );
+
+        fn check<R: RandomSource>() {
+            let n = 2 * pi::BLOCK + 99;
+            let seq = simulate_with::<R>(n, 11, Mode::Sequential);
+            assert_eq!(simulate_with::<R>(n, 11, Mode::Rayon), seq, "{}", R::NAME);
+            assert!(seq.abs_error() < 5. * seq.std_error, "{}: {seq:?}", R::NAME);
+
+            let mut rng = R::from_seed(5);
+            let draws: Vec<f64> = (0..10_000).map(|_| rng.next_f64()).collect();
+            assert!(draws.iter().all(|x| (0. ..1.).contains(x)), "{}", R::NAME);
+            let mean = draws.iter().sum::<f64>() / draws.len() as f64;
+            assert!((mean - 0.5).abs() < 0.02, "{}: mean {mean}", R::NAME);
+        }
+        check::<fastrand::Rng>();
+        check::<SplitMix64>();
+        check::<Xoshiro256PlusPlus>();
+        check::<Pcg64>();
+        check::<Philox4x32>();
+    }
+
+    #[test]
+    fn batched_kernels_agree() {
+        let kernels = batched::available();
+        for n in [0, 1, 3, 4, 5, 1001, pi::BLOCK + 7] {
+            let expected = batched::count_hits(batched::scalar, 17, n);
+            for (name, kernel) in &kernels {
+                assert_eq!(
+                    batched::count_hits(*kernel, 17, n),
+                    expected,
+                    "{name}, n {n}"
+                );
+            }
+        }
+
+        let n = 3 * pi::BLOCK + 2;
+        for (name, kernel) in kernels {
+            let single = batched::simulate_batched(kernel, n, 4);
+            assert!(
+                single.abs_error() < 5. * single.std_error,
+                "{name}: {single:?}"
+            );
+            let par = batched::simulate_batched_rayon(kernel, n, 4);
+            assert_eq!(par, batched::simulate_batched_rayon(batched::scalar, n, 4));
+            assert!(par.abs_error() < 5. * par.std_error, "{name}: {par:?}");
+        }
+    }
+
+    #[test]
+    fn adaptive_stops_at_target_or_budget() {
+        use adaptive::*;
+        use integrate::Mode;
+        use std::time::Duration;
+
+        let budget = Budget {
+            max_samples: 1 << 24,
+            max_time: None,
+        };
+        for mode in [Mode::Sequential, Mode::Rayon] {
+            let res = estimate_pi_adaptive(Target::HalfWidth95(4e-3), budget, 4, 3, mode);
+            let est = &res.estimate;
+            assert!(res.met);
+            assert!(1.96 * est.std_error <= 4e-3);
+            assert!(est.abs_error() < 5. * est.std_error, "{est:?}");
+            // same blocks as a fixed size run of the same length
+            let kernel = batched::detect().1;
+            let fixed = batched::simulate_batched_rayon(kernel, est.trials as usize, 3);
+            assert_eq!(est.hits, fixed.hits);
+        }
+
+        // unreachable target: stops at the sample budget
+        let small = Budget {
+            max_samples: 8 * pi::BLOCK as u64,
+            max_time: None,
+        };
+        let res = estimate_pi_adaptive(Target::StdError(1e-9), small, 3, 3, Mode::Rayon);
+        assert!(!res.met);
+        assert_eq!(res.estimate.trials, small.max_samples);
+        // 1 to get a variance, then capped at 3
+        assert_eq!(res.batches, 4);
+
+        // or at the time budget
+        let timed = Budget {
+            max_samples: u64::MAX,
+            max_time: Some(Duration::ZERO),
+        };
+        let res = estimate_pi_adaptive(Target::StdError(1e-9), timed, 3, 3, Mode::Rayon);
+        assert!(!res.met);
+        assert_eq!(res.estimate.trials, 0);
+    }
+
+    #[test]
+    fn workloads_match_closed_forms() {
+        use integrate::Mode;
+        use workloads::*;
+
+        assert!((norm_cdf(0.) - 0.5).abs() < 1e-7);
+        assert!((norm_cdf(1.96) - 0.9750021).abs() < 1e-6);
+        assert!((norm_cdf(-1.) - 0.1586553).abs() < 1e-6);
+
+        let option = CallOption {
+            spot: 100.,
+            strike: 100.,
+            rate: 0.05,
+            vol: 0.2,
+            maturity: 1.,
+        };
+        assert!((option.black_scholes() - 10.450584).abs() < 1e-4);
+
+        let within = |res: integrate::Integral, exact: f64| {
+            assert!(
+                (res.estimate - exact).abs() < 4. * res.std_error,
+                "{res:?} vs {exact}"
+            );
+        };
+        let n = 200000;
+        let euro = price_european(&option, n, 7, Mode::Rayon);
+        within(euro, option.black_scholes());
+        assert_eq!(euro, price_european(&option, n, 7, Mode::Sequential));
+
+        let geo = price_asian(&option, 16, Average::Geometric, n / 16, 7, Mode::Rayon);
+        within(geo, option.geometric_asian(16));
+        // same paths, and the arithmetic mean is never below the geometric one
+        let arith = price_asian(&option, 16, Average::Arithmetic, n / 16, 7, Mode::Rayon);
+        assert!(arith.estimate > geo.estimate && arith.estimate < euro.estimate);
+
+        let msd = random_walk_msd(100, 20000, 7, Mode::Rayon);
+        within(msd, 100.);
+        assert_eq!(msd, random_walk_msd(100, 20000, 7, Mode::Sequential));
+
+        let needle = buffon(0.8, 1., n, 7, Mode::Rayon);
+        within(needle, std::f64::consts::PI);
+        assert_eq!(needle, buffon(0.8, 1., n, 7, Mode::Sequential));
+    }
 }
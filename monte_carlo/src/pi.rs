@@ -0,0 +1,217 @@
+//! Monte Carlo estimation of pi, sequential and rayon-parallel.
+//!
+//! The parallel estimator splits the samples into fixed-size blocks and gives every block its own
+//! RNG, seeded from (seed, block index). Which thread runs which block doesn't matter, so the
+//! estimate only depends on the seed and the number of samples, not on the thread count.
+#![allow(dead_code)]
+
+use fastrand::{Rng, f32};
+use rayon::prelude::*;
+
+/// Monte carlo approximation of pi on single thread, f32 points from fastrand's thread-local
+/// generator. Integer counts, so it keeps counting past 2^24 samples where an f32 tally stalls.
+pub fn simulate(max_iter: usize) -> PiEstimate {
+    let mut hits: u64 = 0;
+    for _ in 0..max_iter {
+        let x = f32();
+        let y = f32();
+        // predication pattern
+        hits += (x * x + y * y < 1.) as u64;
+    }
+    PiEstimate::new(hits, max_iter as u64, None)
+}
+
+/// Samples drawn from one RNG stream, also the unit of work for rayon.
+pub const BLOCK: usize = 1 << 16;
+
+/// SplitMix64 finalizer, turns consecutive block indices into unrelated seeds.
+pub fn splitmix64(mut z: u64) -> u64 {
+    z = z.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// The RNG for block `block` of a run seeded with `seed`.
+pub fn block_rng(seed: u64, block: u64) -> Rng {
+    Rng::with_seed(splitmix64(seed ^ splitmix64(block)))
+}
+
+/// Points out of `n` that land inside the quarter circle.
+#[inline(always)]
+pub fn count_hits(rng: &mut Rng, n: usize) -> u64 {
+    let mut hits = 0;
+    for _ in 0..n {
+        let x = rng.f64();
+        let y = rng.f64();
+        // predication pattern
+        hits += (x * x + y * y < 1.) as u64;
+    }
+    hits
+}
+
+/// One point of the convergence trace.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Checkpoint {
+    pub trials: u64,
+    pub estimate: f64,
+    pub std_error: f64,
+}
+
+/// Estimate with its uncertainty. Each trial is a Bernoulli(pi / 4), so the standard error of
+/// 4 * hits / trials is 4 * sqrt(p (1 - p) / trials).
+#[derive(Debug, Clone, PartialEq)]
+pub struct PiEstimate {
+    pub hits: u64,
+    pub trials: u64,
+    pub estimate: f64,
+    pub std_error: f64,
+    /// normal approximation, estimate +- 1.96 standard errors
+    pub ci95: (f64, f64),
+    /// estimate at log-spaced trial counts, only if asked for
+    pub trace: Option<Vec<Checkpoint>>,
+}
+
+fn estimate_and_error(hits: u64, trials: u64) -> (f64, f64) {
+    if trials == 0 {
+        return (f64::NAN, f64::NAN);
+    }
+    let p = hits as f64 / trials as f64;
+    (4. * p, 4. * (p * (1. - p) / trials as f64).sqrt())
+}
+
+impl PiEstimate {
+    pub fn new(hits: u64, trials: u64, trace: Option<Vec<Checkpoint>>) -> Self {
+        let (estimate, std_error) = estimate_and_error(hits, trials);
+        PiEstimate {
+            hits,
+            trials,
+            estimate,
+            std_error,
+            ci95: (estimate - 1.96 * std_error, estimate + 1.96 * std_error),
+            trace,
+        }
+    }
+
+    pub fn abs_error(&self) -> f64 {
+        (self.estimate - std::f64::consts::PI).abs()
+    }
+
+    pub fn ci_covers_pi(&self) -> bool {
+        (self.ci95.0..=self.ci95.1).contains(&std::f64::consts::PI)
+    }
+}
+
+/// `per_decade` log-spaced trial counts from 1 up to and always including `max_iter`.
+pub fn log_checkpoints(max_iter: usize, per_decade: usize) -> Vec<usize> {
+    let per_decade = per_decade.max(1) as f64;
+    let mut out: Vec<usize> = (0..)
+        .map(|k| 10_f64.powf(k as f64 / per_decade).round() as usize)
+        .take_while(|&n| n < max_iter)
+        .collect();
+    out.dedup();
+    if max_iter > 0 {
+        out.push(max_iter);
+    }
+    out
+}
+
+/// Hits of one block, plus the running hit count of the block at every checkpoint inside it.
+/// Counting in segments draws from the same stream, so the total doesn't change with the trace.
+fn block_hits(
+    seed: u64,
+    max_iter: usize,
+    block: usize,
+    checkpoints: &[usize],
+) -> (u64, Vec<(usize, u64)>) {
+    let start = block * BLOCK;
+    let end = (start + BLOCK).min(max_iter);
+    let mut rng = block_rng(seed, block as u64);
+
+    let first = checkpoints.partition_point(|&c| c <= start);
+    let last = checkpoints.partition_point(|&c| c <= end);
+    let mut marks = Vec::with_capacity(last - first);
+    let (mut pos, mut hits) = (start, 0);
+    for &c in &checkpoints[first..last] {
+        hits += count_hits(&mut rng, c - pos);
+        marks.push((c, hits));
+        pos = c;
+    }
+    (hits + count_hits(&mut rng, end - pos), marks)
+}
+
+fn n_blocks(max_iter: usize) -> usize {
+    max_iter.div_ceil(BLOCK)
+}
+
+/// Joins per-block results, in block order, into the final estimate.
+fn collect_blocks(
+    blocks: Vec<(u64, Vec<(usize, u64)>)>,
+    max_iter: usize,
+    traced: bool,
+) -> PiEstimate {
+    let mut total = 0;
+    let mut trace = Vec::new();
+    for (hits, marks) in blocks {
+        for (trials, prefix) in marks {
+            let (estimate, std_error) = estimate_and_error(total + prefix, trials as u64);
+            trace.push(Checkpoint {
+                trials: trials as u64,
+                estimate,
+                std_error,
+            });
+        }
+        total += hits;
+    }
+    PiEstimate::new(total, max_iter as u64, traced.then_some(trace))
+}
+
+/// Single-threaded, same blocks and streams as `simulate_rayon`, so both agree exactly.
+/// `trace` is the number of convergence checkpoints per decade of trials.
+pub fn simulate_seeded(max_iter: usize, seed: u64, trace: Option<usize>) -> PiEstimate {
+    let checkpoints = trace.map_or(Vec::new(), |d| log_checkpoints(max_iter, d));
+    let blocks = (0..n_blocks(max_iter))
+        .map(|b| block_hits(seed, max_iter, b, &checkpoints))
+        .collect();
+    collect_blocks(blocks, max_iter, trace.is_some())
+}
+
+/// One block per rayon job, reproducible for a given seed on any number of threads.
+pub fn simulate_rayon(max_iter: usize, seed: u64, trace: Option<usize>) -> PiEstimate {
+    let checkpoints = trace.map_or(Vec::new(), |d| log_checkpoints(max_iter, d));
+    let blocks = (0..n_blocks(max_iter))
+        .into_par_iter()
+        .map(|b| block_hits(seed, max_iter, b, &checkpoints))
+        .collect();
+    collect_blocks(blocks, max_iter, trace.is_some())
+}
+
+pub fn print_accuracy_report(sizes: &[usize], seed: u64) {
+    println!("MONTE-CARLO ACCURACY");
+    println!(
+        "{:>10} {:>12} {:>12} {:>26} {:>12} {:>8}",
+        "trials", "estimate", "std_error", "ci95", "abs_error", "covers"
+    );
+    for &size in sizes {
+        let est = simulate_rayon(size, seed, None);
+        println!(
+            "{:>10} {:>12.8} {:>12.3e} {:>26} {:>12.3e} {:>8}",
+            size,
+            est.estimate,
+            est.std_error,
+            format!("[{:.6}, {:.6}]", est.ci95.0, est.ci95.1),
+            est.abs_error(),
+            est.ci_covers_pi()
+        );
+    }
+
+    let largest = sizes.iter().copied().max().unwrap_or(0);
+    let traced = simulate_rayon(largest, seed, Some(2));
+    println!("Convergence trace for {} trials", largest);
+    for c in traced.trace.unwrap_or_default() {
+        println!(
+            "{:>10} {:>12.8} {:>12.3e}",
+            c.trials, c.estimate, c.std_error
+        );
+    }
+}
@@ -0,0 +1,310 @@
+//! Quasi-Monte Carlo: low-discrepancy point sets as sample sources for the integration API.
+//!
+//! Both sequences are index addressable, point i is computed directly rather than from point
+//! i - 1, so a rayon block can start anywhere. A deterministic QMC estimate has no error bar;
+//! randomizing the sequence (random shift, or a digital shift for Sobol) and averaging a few
+//! independent replicates gives one back, while keeping the O(log^d n / n) convergence.
+#![allow(dead_code)]
+
+use fastrand::Rng;
+use rayon::prelude::*;
+
+use crate::integrate::{GAUSS_01, Integral, Integrand, Mode, Moments, integrate_with, volume};
+use crate::pi::{BLOCK, splitmix64};
+
+pub trait Sequence: Sync {
+    fn dim(&self) -> usize;
+
+    /// Writes point `i` of the sequence, in [0, 1)^dim, into `x`.
+    fn point(&self, i: u64, x: &mut [f64]);
+}
+
+const PRIMES: [u64; 16] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53];
+
+/// Van der Corput radical inverse of i in base b.
+fn radical_inverse(mut i: u64, base: u64) -> f64 {
+    let inv = 1. / base as f64;
+    let (mut res, mut f) = (0., inv);
+    while i > 0 {
+        res += (i % base) as f64 * f;
+        i /= base;
+        f *= inv;
+    }
+    res
+}
+
+/// Radical inverses in pairwise coprime bases, one base per dimension. Starts at index 1, the
+/// all-zero point at index 0 is skipped.
+pub struct Halton {
+    pub bases: Vec<u64>,
+}
+
+impl Halton {
+    /// The first `dim` primes as bases.
+    pub fn new(dim: usize) -> Result<Halton, String> {
+        if dim > PRIMES.len() {
+            return Err(format!(
+                "Halton is only set up for up to {} dimensions, got {}",
+                PRIMES.len(),
+                dim
+            ));
+        }
+        Ok(Halton {
+            bases: PRIMES[..dim].to_vec(),
+        })
+    }
+
+    pub fn with_bases(bases: Vec<u64>) -> Result<Halton, String> {
+        if bases.iter().any(|&b| b < 2) {
+            return Err("Halton bases have to be at least 2".to_string());
+        }
+        Ok(Halton { bases })
+    }
+}
+
+impl Sequence for Halton {
+    fn dim(&self) -> usize {
+        self.bases.len()
+    }
+
+    fn point(&self, i: u64, x: &mut [f64]) {
+        for (xi, &b) in x.iter_mut().zip(&self.bases) {
+            *xi = radical_inverse(i + 1, b);
+        }
+    }
+}
+
+const SOBOL_BITS: usize = 32;
+
+/// Joe and Kuo's new-joe-kuo-6.21201 parameters for dimensions 2 to 16: degree s of the
+/// primitive polynomial, its coefficients a and the initial direction numbers m.
+const JOE_KUO: [(usize, u32, &[u32]); 15] = [
+    (1, 0, &[1]),
+    (2, 1, &[1, 3]),
+    (3, 1, &[1, 3, 1]),
+    (3, 2, &[1, 1, 1]),
+    (4, 1, &[1, 1, 3, 3]),
+    (4, 4, &[1, 3, 5, 13]),
+    (5, 2, &[1, 1, 5, 5, 17]),
+    (5, 4, &[1, 1, 5, 5, 5]),
+    (5, 7, &[1, 1, 7, 11, 19]),
+    (5, 11, &[1, 1, 5, 1, 1]),
+    (5, 13, &[1, 1, 1, 3, 11]),
+    (5, 14, &[1, 3, 5, 5, 31]),
+    (6, 1, &[1, 3, 3, 9, 7, 49]),
+    (6, 13, &[1, 1, 1, 15, 21, 21]),
+    (6, 16, &[1, 3, 1, 13, 27, 49]),
+];
+
+/// Sobol sequence with Joe-Kuo direction numbers, 32 bits per coordinate, in Gray code order.
+/// `shift` is XORed into every coordinate, all zeros is the plain sequence.
+#[derive(Clone)]
+pub struct Sobol {
+    directions: Vec<[u32; SOBOL_BITS]>,
+    shift: Vec<u32>,
+}
+
+impl Sobol {
+    pub fn new(dim: usize) -> Result<Sobol, String> {
+        if dim > JOE_KUO.len() + 1 {
+            return Err(format!(
+                "Sobol is only set up for up to {} dimensions, got {}",
+                JOE_KUO.len() + 1,
+                dim
+            ));
+        }
+
+        let mut directions = Vec::with_capacity(dim);
+        if dim > 0 {
+            // the first dimension is plain van der Corput in base 2
+            directions.push(std::array::from_fn(|k| 1 << (SOBOL_BITS - 1 - k)));
+        }
+        for &(s, a, init) in JOE_KUO.iter().take(dim.saturating_sub(1)) {
+            let mut m = [0_u32; SOBOL_BITS];
+            m[..s].copy_from_slice(init);
+            for k in s..SOBOL_BITS {
+                let mut next = m[k - s] ^ (m[k - s] << s);
+                for j in 1..s {
+                    // bit j of a, most significant first, picks 2^j m_{k-j}
+                    if (a >> (s - 1 - j)) & 1 == 1 {
+                        next ^= m[k - j] << j;
+                    }
+                }
+                m[k] = next;
+            }
+            directions.push(std::array::from_fn(|k| m[k] << (SOBOL_BITS - 1 - k)));
+        }
+
+        Ok(Sobol {
+            directions,
+            shift: vec![0; dim],
+        })
+    }
+
+    /// Random digital shift: a random 32-bit mask XORed into every coordinate. Keeps the net
+    /// structure of the sequence, unlike an additive shift.
+    pub fn digitally_shifted(&self, rng: &mut Rng) -> Sobol {
+        Sobol {
+            directions: self.directions.clone(),
+            shift: (0..self.directions.len()).map(|_| rng.u32(..)).collect(),
+        }
+    }
+}
+
+impl Sequence for Sobol {
+    fn dim(&self) -> usize {
+        self.directions.len()
+    }
+
+    fn point(&self, i: u64, x: &mut [f64]) {
+        let gray = i ^ (i >> 1);
+        for ((xi, v), s) in x.iter_mut().zip(&self.directions).zip(&self.shift) {
+            let mut bits = *s;
+            let mut g = gray;
+            let mut k = 0;
+            while g != 0 && k < SOBOL_BITS {
+                if g & 1 == 1 {
+                    bits ^= v[k];
+                }
+                g >>= 1;
+                k += 1;
+            }
+            *xi = bits as f64 / (1_u64 << SOBOL_BITS) as f64;
+        }
+    }
+}
+
+/// Cranley-Patterson rotation: every point moved by the same random offset, modulo 1.
+pub struct RandomShift<S> {
+    pub inner: S,
+    pub shift: Vec<f64>,
+}
+
+impl<S: Sequence> RandomShift<S> {
+    pub fn new(inner: S, rng: &mut Rng) -> Self {
+        let shift = (0..inner.dim()).map(|_| rng.f64()).collect();
+        RandomShift { inner, shift }
+    }
+}
+
+impl<S: Sequence> Sequence for RandomShift<S> {
+    fn dim(&self) -> usize {
+        self.inner.dim()
+    }
+
+    fn point(&self, i: u64, x: &mut [f64]) {
+        self.inner.point(i, x);
+        for (xi, s) in x.iter_mut().zip(&self.shift) {
+            *xi = (*xi + s).fract();
+        }
+    }
+}
+
+/// Mean of f over the first `n` points of `seq`, mapped into `bounds`, times the volume.
+/// Deterministic, so the reported standard error is only the sample spread as if it were
+/// random and says nothing about the actual error.
+pub fn integrate_qmc<F, S>(f: F, bounds: &[(f64, f64)], n: usize, seq: &S, mode: Mode) -> Integral
+where
+    F: Fn(&[f64]) -> f64 + Sync,
+    S: Sequence,
+{
+    assert_eq!(
+        seq.dim(),
+        bounds.len(),
+        "sequence and bounds dimensions differ"
+    );
+    let block = |b: usize| {
+        let mut x = vec![0.; bounds.len()];
+        let mut m = Moments::default();
+        for i in b * BLOCK..((b + 1) * BLOCK).min(n) {
+            seq.point(i as u64, &mut x);
+            for (xi, (lo, hi)) in x.iter_mut().zip(bounds) {
+                *xi = lo + (hi - lo) * *xi;
+            }
+            m.push(f(&x));
+        }
+        m
+    };
+    let per_block: Vec<Moments> = match mode {
+        Mode::Sequential => (0..n.div_ceil(BLOCK)).map(block).collect(),
+        Mode::Rayon => (0..n.div_ceil(BLOCK)).into_par_iter().map(block).collect(),
+    };
+    let m = per_block
+        .into_iter()
+        .fold(Moments::default(), Moments::merge);
+    Integral::from_moments(m, volume(bounds))
+}
+
+/// Randomized QMC: `replicates` independent randomizations of the sequence, `n` points each.
+/// The estimate is their mean and the standard error comes from their spread, which unlike
+/// `integrate_qmc`'s is a real error estimate.
+pub fn integrate_rqmc<F, S, R>(
+    f: F,
+    bounds: &[(f64, f64)],
+    n: usize,
+    replicates: usize,
+    seed: u64,
+    mode: Mode,
+    randomize: R,
+) -> Integral
+where
+    F: Fn(&[f64]) -> f64 + Sync,
+    S: Sequence,
+    R: Fn(&mut Rng) -> S,
+{
+    let replicates = replicates.max(2);
+    let mut m = Moments::default();
+    for r in 0..replicates {
+        let seq = randomize(&mut Rng::with_seed(splitmix64(seed ^ r as u64)));
+        m.push(integrate_qmc(&f, bounds, n, &seq, mode).estimate);
+    }
+
+    let std_error = (m.variance() / replicates as f64).sqrt();
+    Integral {
+        samples: (n * replicates) as u64,
+        estimate: m.mean,
+        std_error,
+        ci95: (m.mean - 1.96 * std_error, m.mean + 1.96 * std_error),
+    }
+}
+
+pub fn print_qmc_report(max_log2: u32, seed: u64) {
+    println!("MONTE-CARLO QMC CONVERGENCE (abs error)");
+    let quarter = |x: &[f64]| (x[0] * x[0] + x[1] * x[1] < 1.) as u8 as f64 * 4.;
+    let gauss = crate::integrate::gaussian;
+    let problems: [(&str, Integrand, usize, f64); 3] = [
+        ("pi", &quarter, 2, std::f64::consts::PI),
+        ("gaussian 2d", &gauss, 2, GAUSS_01.powi(2)),
+        ("gaussian 5d", &gauss, 5, GAUSS_01.powi(5)),
+    ];
+
+    for (name, f, d, exact) in problems {
+        let unit = vec![(0., 1.); d];
+        let halton = Halton::new(d).expect("dimension checked above");
+        let sobol = Sobol::new(d).expect("dimension checked above");
+
+        println!("{} (exact {:.10})", name, exact);
+        println!(
+            "{:>10} {:>12} {:>12} {:>12} {:>12} {:>12} {:>14}",
+            "n", "fastrand", "halton", "sobol", "rqmc_sobol", "rqmc_se", "log^d(n)/n"
+        );
+        for k in (10..=max_log2).step_by(2) {
+            let n = 1_usize << k;
+            let err = |res: Integral| (res.estimate - exact).abs();
+            // 8 replicates of n / 8 points, same total budget as the other columns
+            let rqmc = integrate_rqmc(f, &unit, n / 8, 8, seed, Mode::Rayon, |rng| {
+                sobol.digitally_shifted(rng)
+            });
+            println!(
+                "{:>10} {:>12.3e} {:>12.3e} {:>12.3e} {:>12.3e} {:>12.3e} {:>14.3e}",
+                n,
+                err(integrate_with(f, &unit, n, seed, Mode::Rayon)),
+                err(integrate_qmc(f, &unit, n, &halton, Mode::Rayon)),
+                err(integrate_qmc(f, &unit, n, &sobol, Mode::Rayon)),
+                err(rqmc),
+                rqmc.std_error,
+                (n as f64).ln().powi(d as i32) / n as f64
+            );
+        }
+    }
+}
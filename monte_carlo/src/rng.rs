@@ -0,0 +1,265 @@
+//! Pluggable random number generators for the samplers.
+//!
+//! `simulate` goes through `fastrand`'s thread-local generator on every draw, so part of what it
+//! measures is the thread-local lookup. Here every generator is a plain value owned by the
+//! sampler, and the sampler is generic over it, so the generator is the only thing that changes
+//! between two runs.
+#![allow(dead_code)]
+
+use rayon::prelude::*;
+
+use crate::integrate::Mode;
+use crate::pi::{BLOCK, PiEstimate, splitmix64};
+
+pub trait RandomSource: Send {
+    const NAME: &'static str;
+
+    fn from_seed(seed: u64) -> Self;
+
+    fn next_u64(&mut self) -> u64;
+
+    /// Uniform in [0, 1) from the top 53 bits.
+    #[inline(always)]
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 * (1. / (1_u64 << 53) as f64)
+    }
+}
+
+/// wyrand, what `fastrand` runs under the hood.
+impl RandomSource for fastrand::Rng {
+    const NAME: &'static str = "fastrand";
+
+    fn from_seed(seed: u64) -> Self {
+        fastrand::Rng::with_seed(seed)
+    }
+
+    #[inline(always)]
+    fn next_u64(&mut self) -> u64 {
+        self.u64(..)
+    }
+}
+
+pub struct SplitMix64 {
+    state: u64,
+}
+
+impl RandomSource for SplitMix64 {
+    const NAME: &'static str = "splitmix64";
+
+    fn from_seed(seed: u64) -> Self {
+        SplitMix64 { state: seed }
+    }
+
+    #[inline(always)]
+    fn next_u64(&mut self) -> u64 {
+        let out = splitmix64(self.state);
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        out
+    }
+}
+
+pub struct Xoshiro256PlusPlus {
+    s: [u64; 4],
+}
+
+impl RandomSource for Xoshiro256PlusPlus {
+    const NAME: &'static str = "xoshiro256++";
+
+    /// State filled from SplitMix64, as the authors recommend, so it is never all zeros.
+    fn from_seed(seed: u64) -> Self {
+        let mut sm = SplitMix64::from_seed(seed);
+        Xoshiro256PlusPlus {
+            s: std::array::from_fn(|_| sm.next_u64()),
+        }
+    }
+
+    #[inline(always)]
+    fn next_u64(&mut self) -> u64 {
+        let s = &mut self.s;
+        let out = s[0].wrapping_add(s[3]).rotate_left(23).wrapping_add(s[0]);
+        let t = s[1] << 17;
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+        out
+    }
+}
+
+/// PCG XSL RR 128/64, numpy's default bit generator.
+pub struct Pcg64 {
+    state: u128,
+    inc: u128,
+}
+
+const PCG_MULT: u128 = 0x2360_ED05_1FC6_5DA4_4385_DF64_9FCC_F645;
+
+impl RandomSource for Pcg64 {
+    const NAME: &'static str = "pcg64";
+
+    fn from_seed(seed: u64) -> Self {
+        let mut sm = SplitMix64::from_seed(seed);
+        let wide = |sm: &mut SplitMix64| ((sm.next_u64() as u128) << 64) | sm.next_u64() as u128;
+        let (init, seq) = (wide(&mut sm), wide(&mut sm));
+
+        // the increment has to be odd, seeding follows the reference pcg64_srandom_r
+        let mut rng = Pcg64 {
+            state: 0,
+            inc: (seq << 1) | 1,
+        };
+        rng.next_u64();
+        rng.state = rng.state.wrapping_add(init);
+        rng.next_u64();
+        rng
+    }
+
+    #[inline(always)]
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_mul(PCG_MULT).wrapping_add(self.inc);
+        let xored = ((self.state >> 64) as u64) ^ (self.state as u64);
+        xored.rotate_right((self.state >> 122) as u32)
+    }
+}
+
+/// Philox4x32-10 from Random123. Counter based: output i is a keyed bijection of i, so any
+/// position of the stream can be computed directly without stepping through the ones before.
+pub struct Philox4x32 {
+    key: [u32; 2],
+    counter: u128,
+    buf: [u64; 2],
+    idx: usize,
+}
+
+impl Philox4x32 {
+    /// The four 32-bit outputs for one counter value.
+    pub fn block(key: [u32; 2], counter: [u32; 4]) -> [u32; 4] {
+        const M0: u64 = 0xD251_1F53;
+        const M1: u64 = 0xCD9E_8D57;
+        const W0: u32 = 0x9E37_79B9;
+        const W1: u32 = 0xBB67_AE85;
+
+        let (mut c, mut k) = (counter, key);
+        for _ in 0..10 {
+            let p0 = M0 * c[0] as u64;
+            let p1 = M1 * c[2] as u64;
+            c = [
+                (p1 >> 32) as u32 ^ c[1] ^ k[0],
+                p1 as u32,
+                (p0 >> 32) as u32 ^ c[3] ^ k[1],
+                p0 as u32,
+            ];
+            k = [k[0].wrapping_add(W0), k[1].wrapping_add(W1)];
+        }
+        c
+    }
+
+    fn refill(&mut self) {
+        let c = self.counter;
+        let out = Self::block(
+            self.key,
+            [
+                c as u32,
+                (c >> 32) as u32,
+                (c >> 64) as u32,
+                (c >> 96) as u32,
+            ],
+        );
+        self.buf = [
+            (out[0] as u64) << 32 | out[1] as u64,
+            (out[2] as u64) << 32 | out[3] as u64,
+        ];
+        self.counter = self.counter.wrapping_add(1);
+        self.idx = 0;
+    }
+}
+
+impl RandomSource for Philox4x32 {
+    const NAME: &'static str = "philox4x32";
+
+    fn from_seed(seed: u64) -> Self {
+        Philox4x32 {
+            key: [seed as u32, (seed >> 32) as u32],
+            counter: 0,
+            buf: [0; 2],
+            idx: 2,
+        }
+    }
+
+    #[inline(always)]
+    fn next_u64(&mut self) -> u64 {
+        if self.idx == 2 {
+            self.refill();
+        }
+        self.idx += 1;
+        self.buf[self.idx - 1]
+    }
+}
+
+/// Points out of `n` inside the quarter circle.
+#[inline(always)]
+pub fn count_hits<R: RandomSource>(rng: &mut R, n: usize) -> u64 {
+    let mut hits = 0;
+    for _ in 0..n {
+        let x = rng.next_f64();
+        let y = rng.next_f64();
+        // predication pattern
+        hits += (x * x + y * y < 1.) as u64;
+    }
+    hits
+}
+
+/// The blocked pi estimator with generator `R`, one generator per block seeded from
+/// (seed, block), so the result doesn't depend on the thread count.
+pub fn simulate_with<R: RandomSource>(max_iter: usize, seed: u64, mode: Mode) -> PiEstimate {
+    let block = |b: usize| {
+        let mut rng = R::from_seed(splitmix64(seed ^ splitmix64(b as u64)));
+        count_hits(&mut rng, BLOCK.min(max_iter - b * BLOCK))
+    };
+    let hits: u64 = match mode {
+        Mode::Sequential => (0..max_iter.div_ceil(BLOCK)).map(block).sum(),
+        Mode::Rayon => (0..max_iter.div_ceil(BLOCK))
+            .into_par_iter()
+            .map(block)
+            .sum(),
+    };
+    PiEstimate::new(hits, max_iter as u64, None)
+}
+
+fn report_row<R: RandomSource>(n: usize, seeds: u64, num_runs: usize) {
+    let secs = crate::timing::average_secs(|| simulate_with::<R>(n, 42, Mode::Rayon), num_runs);
+
+    // |estimate - pi| in standard errors, about 0.8 on average for a good generator
+    let mean_z = (0..seeds)
+        .map(|s| {
+            let est = simulate_with::<R>(n, s, Mode::Rayon);
+            est.abs_error() / est.std_error
+        })
+        .sum::<f64>()
+        / seeds as f64;
+    let est = simulate_with::<R>(n, 42, Mode::Rayon);
+
+    println!(
+        "{:>14} {:>12.3e} {:>14.3e} {:>14.10} {:>12.3e} {:>10.3}",
+        R::NAME,
+        secs,
+        n as f64 / secs,
+        est.estimate,
+        est.abs_error(),
+        mean_z
+    );
+}
+
+pub fn print_rng_report(n: usize, seeds: u64, num_runs: usize) {
+    println!("MONTE-CARLO RNG COMPARISON ({} samples)", n);
+    println!(
+        "{:>14} {:>12} {:>14} {:>14} {:>12} {:>10}",
+        "generator", "secs", "samples/s", "estimate", "abs_error", "mean |z|"
+    );
+    report_row::<fastrand::Rng>(n, seeds, num_runs);
+    report_row::<SplitMix64>(n, seeds, num_runs);
+    report_row::<Xoshiro256PlusPlus>(n, seeds, num_runs);
+    report_row::<Pcg64>(n, seeds, num_runs);
+    report_row::<Philox4x32>(n, seeds, num_runs);
+}
@@ -0,0 +1,17 @@
+//! Timing helpers for the one-shot reports, shared with the benches that pull in report code.
+#![allow(dead_code)]
+
+use std::time::{Duration, Instant};
+
+pub fn measure_raw<T>(mut f: impl FnMut() -> T) -> Duration {
+    let start = Instant::now();
+    std::hint::black_box(f());
+    Instant::now() - start
+}
+
+pub fn average_secs<T>(mut f: impl FnMut() -> T, num_runs: usize) -> f64 {
+    (0..num_runs)
+        .map(|_| measure_raw(&mut f).as_secs_f64())
+        .sum::<f64>()
+        / num_runs as f64
+}
@@ -0,0 +1,42 @@
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+
+#[path = "../src/inputs.rs"]
+mod inputs;
+#[path = "../src/quicksort.rs"]
+mod quicksort;
+
+use inputs::{Distribution, generate};
+use quicksort::{Pivot, quicksort_copy_pivot};
+
+fn criterion_config() -> Criterion {
+    Criterion::default().sample_size(10)
+}
+
+/// Pivot strategy x input distribution x size. Kept small, `last` is quadratic on sorted input.
+pub fn quicksort_bench_pivots(c: &mut Criterion) {
+    let mut group = c.benchmark_group("QS-pivots");
+
+    for &size in &[10000, 100000] {
+        group.throughput(Throughput::Elements(size as u64));
+        for dist in Distribution::ALL {
+            let input = generate(dist, size, 42);
+            for pivot in Pivot::ALL {
+                group.bench_with_input(
+                    BenchmarkId::new(format!("{} {}", pivot.name(), dist.name()), size),
+                    &input,
+                    |b, input| {
+                        b.iter_batched(
+                            || input.clone(),
+                            |mut arr| quicksort_copy_pivot(&mut arr, pivot),
+                            criterion::BatchSize::LargeInput,
+                        );
+                    },
+                );
+            }
+        }
+    }
+    group.finish();
+}
+
+criterion_group! {name = benches; config = criterion_config(); targets = quicksort_bench_pivots}
+criterion_main!(benches);
@@ -0,0 +1,46 @@
+//! Generated inputs for comparing pivot strategies, including the ones that break a naive pivot.
+#![allow(dead_code)]
+
+use fastrand::Rng;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Distribution {
+    Random,
+    Sorted,
+    Reversed,
+    /// ascending then descending, fools median of three on first, middle and last
+    OrganPipe,
+    /// 16 distinct values
+    FewUnique,
+}
+
+impl Distribution {
+    pub const ALL: [Distribution; 5] = [
+        Distribution::Random,
+        Distribution::Sorted,
+        Distribution::Reversed,
+        Distribution::OrganPipe,
+        Distribution::FewUnique,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Distribution::Random => "random",
+            Distribution::Sorted => "sorted",
+            Distribution::Reversed => "reversed",
+            Distribution::OrganPipe => "organ_pipe",
+            Distribution::FewUnique => "few_unique",
+        }
+    }
+}
+
+pub fn generate(dist: Distribution, n: usize, seed: u64) -> Vec<usize> {
+    let mut rng = Rng::with_seed(seed);
+    match dist {
+        Distribution::Random => (0..n).map(|_| rng.usize(0..n)).collect(),
+        Distribution::Sorted => (0..n).collect(),
+        Distribution::Reversed => (0..n).rev().collect(),
+        Distribution::OrganPipe => (0..n).map(|i| i.min(n - 1 - i)).collect(),
+        Distribution::FewUnique => (0..n).map(|_| rng.usize(0..16)).collect(),
+    }
+}
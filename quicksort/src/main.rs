@@ -1,3 +1,6 @@
+mod inputs;
+mod quicksort;
+
 use bytemuck::cast_slice;
 use fastrand::usize;
 use std::{
@@ -6,9 +9,12 @@ use std::{
     time::{Duration, Instant},
 };
 
+use inputs::{Distribution, generate};
+use quicksort::{Pivot, quicksort_copy, quicksort_copy_pivot};
+
 pub fn output_arrays() -> std::io::Result<()> {
     for &size in &[100000, 1000000, 10000000, 50000000, 100000000] {
-        let mut arr: Vec<usize> = (0..size).into_iter().map(|_| usize(0..size)).collect();
+        let arr: Vec<usize> = (0..size).map(|_| usize(0..size)).collect();
 
         let file = File::create(format!("/home/aperiax/School/SVK/arr_{}", size))?;
         let mut writer = BufWriter::new(file);
@@ -30,47 +36,51 @@ fn read_array(size: &usize) -> std::io::Result<Vec<usize>> {
     Ok(arr.to_vec())
 }
 
-fn partition(arr: &mut [usize], low: usize, high: usize) -> usize {
-    let pivot: usize = arr[high];
-    let mut i: usize = low;
-
-    for j in low..high {
-        let mask = (arr[j] <= pivot) as usize;
-        arr.swap(i * mask + j * (1 - mask), j);
-        i += mask;
-    }
-    arr.swap(i, high);
-    i
+fn check_correct<T: Ord>(arr: &[T]) {
+    assert!(arr.windows(2).all(|w| w[0] <= w[1]), "Wrong order!")
 }
 
-fn quicksort(arr: &mut [usize], mut low: usize, mut high: usize) {
-    while low < high {
-        let p = partition(arr, low, high);
-        if p - low < high - p {
-            if p > 0 {
-                quicksort(arr, low, p - 1);
-            }
-            low = p + 1;
-        } else {
-            quicksort(arr, p + 1, high);
-            if p == 0 {
-                break;
-            }
-            high = p - 1;
-        }
-    }
+fn measure_raw<T>(mut f: impl FnMut() -> T) -> Duration {
+    let start = Instant::now();
+    std::hint::black_box(f());
+    Instant::now() - start
 }
 
-fn check_correct(arr: &[usize]) {
-    for ind in 0..arr.len() - 1 {
-        assert!(arr[ind] <= arr[ind + 1], "Wrong order!")
+/// Timed runs per cell of `dist`'s row. The ordered inputs are quadratic for `last` (and organ
+/// pipe for median of three), so their rows get a single run; every cell in a row gets the same.
+fn runs_for(dist: Distribution, num_runs: usize) -> usize {
+    match dist {
+        Distribution::Sorted | Distribution::Reversed | Distribution::OrganPipe => 1,
+        Distribution::Random | Distribution::FewUnique => num_runs,
     }
 }
 
-fn measure_raw<T>(mut f: impl FnMut() -> T) -> Duration {
-    let start = Instant::now();
-    let r = f();
-    Instant::now() - start
+/// Average seconds per sort for every pivot strategy on every input distribution.
+fn print_pivot_report(size: usize, num_runs: usize) {
+    println!("QUICKSORT PIVOTS ({} elements, secs)", size);
+    print!("{:>12} {:>5}", "input", "runs");
+    for pivot in Pivot::ALL {
+        print!(" {:>12}", pivot.name());
+    }
+    println!();
+    for dist in Distribution::ALL {
+        let input = generate(dist, size, 42);
+        let runs = runs_for(dist, num_runs);
+        print!("{:>12} {:>5}", dist.name(), runs);
+        for pivot in Pivot::ALL {
+            let secs = (0..runs)
+                .map(|_| {
+                    let mut arr = input.clone();
+                    let res = measure_raw(|| quicksort_copy_pivot(&mut arr, pivot));
+                    check_correct(&arr);
+                    res.as_secs_f64()
+                })
+                .sum::<f64>()
+                / runs as f64;
+            print!(" {:>12.3e}", secs);
+        }
+        println!();
+    }
 }
 
 fn main() {
@@ -79,13 +89,11 @@ fn main() {
     let mut res_vec: Vec<f64> = Vec::with_capacity(5);
     for &size in &[100000, 1000000, 10000000, 50000000, 100000000] {
         // println!("{size}");
-        let n = size - 1;
         let mut temp: Vec<Duration> = Vec::with_capacity(10);
-        for run in 0..num_runs {
-            // println!("{run}");
+        for _ in 0..num_runs {
             let mut arr: Vec<usize> = read_array(&size).ok().unwrap();
             println!("{:?}", &arr[0..20]);
-            let res = measure_raw(|| quicksort(&mut arr, 0, n));
+            let res = measure_raw(|| quicksort_copy(&mut arr));
             check_correct(&arr);
             temp.push(res)
         }
@@ -96,4 +104,104 @@ fn main() {
     println!("tested sizes: 1e5, 1e6, 1e5, 5e7, 1e8");
     println!("Results quickshot: {:?}", res_vec);
     println!("QUICKSORT");
+
+    print_pivot_report(20000, num_runs);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quicksort::{quicksort, quicksort_by, quicksort_by_key, quicksort_pivot};
+
+    fn check_against_std<T: Ord + Clone + std::fmt::Debug>(arr: &[T]) {
+        let mut expected = arr.to_vec();
+        expected.sort();
+        let mut got = arr.to_vec();
+        quicksort(&mut got);
+        assert_eq!(got, expected);
+    }
+
+    /// Both partitions, the branchless one only exists for `Copy` types.
+    fn check_copy_against_std<T: Ord + Copy + std::fmt::Debug>(arr: &[T]) {
+        check_against_std(arr);
+        let mut expected = arr.to_vec();
+        expected.sort();
+        let mut got = arr.to_vec();
+        quicksort_copy(&mut got);
+        assert_eq!(got, expected);
+    }
+
+    #[test]
+    fn sorts_edge_cases_and_any_ord_type() {
+        check_copy_against_std::<usize>(&[]);
+        check_copy_against_std(&[7_usize]);
+        check_copy_against_std(&[3_usize; 1000]);
+        check_copy_against_std(&(0..1000_usize).collect::<Vec<_>>());
+        check_copy_against_std(&(0..1000_usize).rev().collect::<Vec<_>>());
+        check_copy_against_std(&(0..10000).map(|_| usize(0..100)).collect::<Vec<_>>());
+        check_copy_against_std(&(0..10000).map(|_| fastrand::i64(..)).collect::<Vec<_>>());
+
+        // not Copy, only the branchy partition applies
+        let words: Vec<String> = (0..1000).map(|_| usize(0..500).to_string()).collect();
+        check_against_std(&words);
+        check_against_std::<String>(&[]);
+        check_against_std(&vec!["same".to_string(); 100]);
+
+        let mut desc: Vec<u32> = (0..1000).map(|_| fastrand::u32(..)).collect();
+        quicksort_by(&mut desc, |a, b| b.cmp(a));
+        assert!(desc.windows(2).all(|w| w[0] >= w[1]));
+
+        let mut pairs: Vec<(u8, &str)> = vec![(3, "c"), (1, "a"), (2, "b"), (1, "z")];
+        quicksort_by_key(&mut pairs, |p| p.1);
+        assert_eq!(pairs, [(1, "a"), (2, "b"), (3, "c"), (1, "z")]);
+
+        let mut floats = vec![2.5_f64, -1., 0., 10.];
+        quicksort_by(&mut floats, f64::total_cmp);
+        assert_eq!(floats, [-1., 0., 2.5, 10.]);
+    }
+
+    #[test]
+    fn every_pivot_sorts_every_distribution() {
+        use quicksort::quicksort_by_pivot;
+
+        for pivot in Pivot::ALL {
+            for n in [0, 1, 2, 3, 5, 6, 7, 127, 128, 129, 3000] {
+                for dist in Distribution::ALL {
+                    let mut arr = generate(dist, n, 1);
+                    let mut expected = arr.clone();
+                    expected.sort();
+                    let mut copy = arr.clone();
+                    quicksort_pivot(&mut arr, pivot);
+                    quicksort_copy_pivot(&mut copy, pivot);
+                    assert_eq!(arr, expected, "{:?} on {:?}, n = {}", pivot, dist, n);
+                    assert_eq!(copy, expected, "copy, {:?} on {:?}, n = {}", pivot, dist, n);
+                }
+            }
+            let mut words: Vec<String> = (0..500).map(|i| (i * 7919 % 500).to_string()).collect();
+            let mut expected = words.clone();
+            expected.sort_by(|a, b| b.cmp(a));
+            quicksort_by_pivot(&mut words, pivot, |a, b| b.cmp(a));
+            assert_eq!(words, expected, "{:?}", pivot);
+        }
+    }
+
+    #[test]
+    fn equal_keys_stay_linearithmic() {
+        use quicksort::quicksort_by_pivot;
+
+        let n = 100000;
+        for pivot in Pivot::ALL {
+            for input in [generate(Distribution::FewUnique, n, 1), vec![3; n]] {
+                let mut arr = input;
+                let mut comparisons = 0_usize;
+                quicksort_by_pivot(&mut arr, pivot, |a, b| {
+                    comparisons += 1;
+                    a.cmp(b)
+                });
+                check_correct(&arr);
+                // the old `<=`-only partition needed about n^2 / 32 here
+                assert!(comparisons < 40 * n, "{:?}: {}", pivot, comparisons);
+            }
+        }
+    }
 }
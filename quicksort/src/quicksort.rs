@@ -0,0 +1,284 @@
+//! In-place quicksort over slices: Lomuto partition, recursion on the smaller side and a loop on
+//! the larger one, so the stack stays O(log n) deep.
+//!
+//! The pivot strategy only picks which element gets swapped to the end before partitioning.
+//! `Last` is the original behaviour and goes quadratic on sorted and reverse-sorted input.
+//!
+//! Many equal keys don't go quadratic with any strategy. The `<=` partition puts every copy of
+//! the pivot on the left, and a left side whose own pivot turns out equal to the enclosing one
+//! holds nothing bigger, so a strict `<` partition gathers all those copies at its end and they
+//! drop out in one pass (the equal-run skip from pdqsort, mirrored).
+#![allow(dead_code)]
+
+use std::cmp::Ordering;
+use std::mem;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pivot {
+    Last,
+    /// uniformly random position, from fastrand's thread-local generator
+    Random,
+    /// median of first, middle and last
+    MedianOfThree,
+    /// Tukey's ninther: median of three medians of three, spread over the slice
+    Ninther,
+    /// BFPRT median of medians of 5, always within the middle 40%, at a constant-factor cost
+    MedianOfMedians,
+}
+
+impl Pivot {
+    pub const ALL: [Pivot; 5] = [
+        Pivot::Last,
+        Pivot::Random,
+        Pivot::MedianOfThree,
+        Pivot::Ninther,
+        Pivot::MedianOfMedians,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Pivot::Last => "last",
+            Pivot::Random => "random",
+            Pivot::MedianOfThree => "median3",
+            Pivot::Ninther => "ninther",
+            Pivot::MedianOfMedians => "mom",
+        }
+    }
+}
+
+pub fn quicksort<T: Ord>(arr: &mut [T]) {
+    quicksort_by(arr, T::cmp);
+}
+
+pub fn quicksort_pivot<T: Ord>(arr: &mut [T], pivot: Pivot) {
+    quicksort_by_pivot(arr, pivot, T::cmp);
+}
+
+pub fn quicksort_by<T, F>(arr: &mut [T], cmp: F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    quicksort_by_pivot(arr, Pivot::Last, cmp);
+}
+
+pub fn quicksort_by_pivot<T, F>(arr: &mut [T], pivot: Pivot, mut cmp: F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    sort::<false, _, _>(arr, pivot, &mut cmp, None);
+}
+
+/// `quicksort` with the branchless partition, only worth it for `Copy` types.
+pub fn quicksort_copy<T: Ord + Copy>(arr: &mut [T]) {
+    quicksort_copy_pivot(arr, Pivot::Last);
+}
+
+pub fn quicksort_copy_pivot<T: Ord + Copy>(arr: &mut [T], pivot: Pivot) {
+    sort::<true, _, _>(arr, pivot, &mut T::cmp, None);
+}
+
+/// The key is computed again on every comparison, like `slice::sort_unstable_by_key`.
+pub fn quicksort_by_key<T, K, F>(arr: &mut [T], mut key: F)
+where
+    K: Ord,
+    F: FnMut(&T) -> K,
+{
+    quicksort_by(arr, |a, b| key(a).cmp(&key(b)));
+}
+
+/// `upper` is the pivot of the enclosing partition when `arr` was its left side, so every
+/// element of `arr` is `<=` it.
+fn sort<'a, const BRANCHLESS: bool, T, F>(
+    mut arr: &'a mut [T],
+    pivot: Pivot,
+    cmp: &mut F,
+    mut upper: Option<&'a T>,
+) where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    while arr.len() > 1 {
+        let high = arr.len() - 1;
+        let chosen = choose_pivot(arr, pivot, cmp);
+        arr.swap(chosen, high);
+        if upper.is_some_and(|u| cmp(&arr[high], u) == Ordering::Equal) {
+            // nothing here is bigger than the pivot, everything from p on is a copy of it
+            let p = partition::<BRANCHLESS, _, _>(arr, cmp, Ordering::Equal);
+            arr = &mut mem::take(&mut arr)[..p];
+            continue;
+        }
+        let p = partition::<BRANCHLESS, _, _>(arr, cmp, Ordering::Greater);
+        let (left, right) = mem::take(&mut arr).split_at_mut(p);
+        let (mid, right) = right.split_at_mut(1);
+        let mid = &mid[0];
+        if left.len() < right.len() {
+            sort::<BRANCHLESS, _, _>(left, pivot, cmp, Some(mid));
+            arr = right;
+        } else {
+            sort::<BRANCHLESS, _, _>(right, pivot, cmp, upper);
+            arr = left;
+            upper = Some(mid);
+        }
+    }
+}
+
+/// Slices shorter than this fall back from ninther to median of three.
+const NINTHER_THRESHOLD: usize = 128;
+
+fn choose_pivot<T, F>(arr: &mut [T], pivot: Pivot, cmp: &mut F) -> usize
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let n = arr.len();
+    match pivot {
+        Pivot::Last => n - 1,
+        Pivot::Random => fastrand::usize(0..n),
+        Pivot::MedianOfThree => median3(arr, 0, n / 2, n - 1, cmp),
+        Pivot::Ninther if n < NINTHER_THRESHOLD => median3(arr, 0, n / 2, n - 1, cmp),
+        Pivot::Ninther => {
+            let step = n / 8;
+            let m = n / 2;
+            let a = median3(arr, 0, step, 2 * step, cmp);
+            let b = median3(arr, m - step, m, m + step, cmp);
+            let c = median3(arr, n - 1 - 2 * step, n - 1 - step, n - 1, cmp);
+            median3(arr, a, b, c, cmp)
+        }
+        Pivot::MedianOfMedians => median_of_medians(arr, cmp),
+    }
+}
+
+/// Index of the median of `arr[a]`, `arr[b]` and `arr[c]`, nothing moves.
+fn median3<T, F>(arr: &[T], a: usize, b: usize, c: usize, cmp: &mut F) -> usize
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let less = |x: usize, y: usize, cmp: &mut F| cmp(&arr[x], &arr[y]) == Ordering::Less;
+    if less(a, b, cmp) {
+        if less(b, c, cmp) {
+            b
+        } else if less(a, c, cmp) {
+            c
+        } else {
+            a
+        }
+    } else if less(a, c, cmp) {
+        a
+    } else if less(b, c, cmp) {
+        c
+    } else {
+        b
+    }
+}
+
+fn insertion_sort<T, F>(arr: &mut [T], cmp: &mut F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    for i in 1..arr.len() {
+        let mut j = i;
+        while j > 0 && cmp(&arr[j - 1], &arr[j]) == Ordering::Greater {
+            arr.swap(j - 1, j);
+            j -= 1;
+        }
+    }
+}
+
+/// Sorts every group of 5 in place, gathers the group medians at the front and selects their
+/// median there. Reorders `arr`, returns where the pivot ended up.
+fn median_of_medians<T, F>(arr: &mut [T], cmp: &mut F) -> usize
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    if arr.len() <= 5 {
+        insertion_sort(arr, cmp);
+        return arr.len() / 2;
+    }
+    let groups = arr.len() / 5;
+    for g in 0..groups {
+        insertion_sort(&mut arr[g * 5..g * 5 + 5], cmp);
+        // slot g belongs to a group whose median is already in front, it's free to overwrite
+        arr.swap(g, g * 5 + 2);
+    }
+    select(&mut arr[..groups], groups / 2, cmp);
+    groups / 2
+}
+
+/// Quickselect with median-of-medians pivots: puts the k-th smallest at `k`, linear worst case.
+/// Partitions three ways, a two-way partition would go quadratic on runs of equal medians.
+fn select<T, F>(mut arr: &mut [T], mut k: usize, cmp: &mut F)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    loop {
+        if arr.len() <= 5 {
+            insertion_sort(arr, cmp);
+            return;
+        }
+        let chosen = median_of_medians(arr, cmp);
+        arr.swap(chosen, 0);
+        let (lt, gt) = partition3(arr, cmp);
+        if k < lt {
+            arr = &mut mem::take(&mut arr)[..lt];
+        } else if k >= gt {
+            arr = &mut mem::take(&mut arr)[gt..];
+            k -= gt;
+        } else {
+            return;
+        }
+    }
+}
+
+/// Dijkstra's three-way partition around `arr[0]`: returns (lt, gt) with everything before lt
+/// smaller than the pivot, lt..gt equal to it and everything from gt on greater.
+fn partition3<T, F>(arr: &mut [T], cmp: &mut F) -> (usize, usize)
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    // arr[lt] is always a copy of the pivot, since lt < i
+    let (mut lt, mut i, mut gt) = (0, 1, arr.len());
+    while i < gt {
+        match cmp(&arr[i], &arr[lt]) {
+            Ordering::Less => {
+                arr.swap(lt, i);
+                lt += 1;
+                i += 1;
+            }
+            Ordering::Greater => {
+                gt -= 1;
+                arr.swap(i, gt);
+            }
+            Ordering::Equal => i += 1,
+        }
+    }
+    (lt, gt)
+}
+
+/// Partitions around the last element and returns where it ends up: everything before compares
+/// below `bound` against the pivot, everything after doesn't. `Ordering::Greater` splits into
+/// `<=` and `>`, `Ordering::Equal` into `<` and `>=`. `BRANCHLESS` swaps every element,
+/// possibly with itself, which only pays off for cheap `Copy` moves.
+fn partition<const BRANCHLESS: bool, T, F>(arr: &mut [T], cmp: &mut F, bound: Ordering) -> usize
+where
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let high = arr.len() - 1;
+    let (rest, pivot) = arr.split_at_mut(high);
+    let pivot = &pivot[0];
+    let mut i: usize = 0;
+
+    if BRANCHLESS {
+        for j in 0..rest.len() {
+            let mask = (cmp(&rest[j], pivot) < bound) as usize;
+            rest.swap(i * mask + j * (1 - mask), j);
+            i += mask;
+        }
+    } else {
+        for j in 0..rest.len() {
+            if cmp(&rest[j], pivot) < bound {
+                rest.swap(i, j);
+                i += 1;
+            }
+        }
+    }
+    arr.swap(i, high);
+    i
+}